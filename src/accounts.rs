@@ -0,0 +1,83 @@
+use super::client;
+use super::context;
+use super::models;
+
+/// Holds credentials of many accounts on top of one shared hyper client.
+pub struct AccountRegistry<TConnector> {
+    client: std::sync::Arc<hyper::Client<TConnector>>,
+    accounts: std::collections::HashMap<String, std::sync::Arc<context::AuthContext>>,
+}
+
+impl<TConnector> AccountRegistry<TConnector>
+where
+    TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
+{
+    pub fn new(
+        client: std::sync::Arc<hyper::Client<TConnector>>,
+    ) -> AccountRegistry<TConnector> {
+        AccountRegistry {
+            client,
+            accounts: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Returns the previous context registered under `label`, if any.
+    pub fn add_account(
+        &mut self,
+        label: String,
+        auth_context: std::sync::Arc<context::AuthContext>,
+    ) -> Option<std::sync::Arc<context::AuthContext>> {
+        self.accounts.insert(label, auth_context)
+    }
+
+    pub fn remove_account(
+        &mut self,
+        label: &str,
+    ) -> Option<std::sync::Arc<context::AuthContext>> {
+        self.accounts.remove(label)
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = &String> {
+        self.accounts.keys()
+    }
+
+    pub fn client(&self, label: &str) -> Option<client::BTCClient<TConnector>> {
+        self.accounts.get(label)
+            .map(|auth_context| client::BTCClient::new(
+                self.client.clone(),
+                auth_context.clone()))
+    }
+
+    pub fn clients(&self) -> impl Iterator<Item = (&String, client::BTCClient<TConnector>)> + '_ {
+        self.accounts.iter()
+            .map(move |(label, auth_context)| (
+                label,
+                client::BTCClient::new(self.client.clone(), auth_context.clone())))
+    }
+
+    pub async fn get_all_account_balances(
+        &self,
+    ) -> Result<std::collections::HashMap<String, models::Balance>, String> {
+        let balances = self.clients()
+            .map(|(label, client)| async move {
+                let balance = client.get_account_balance().await
+                    .map_err(|error| format!("{}: {}", label, error))?;
+                Ok::<_, String>((label.clone(), balance))
+            });
+        let balances = futures::future::try_join_all(balances).await?;
+        Ok(balances.into_iter().collect())
+    }
+
+    pub async fn get_all_trading_balances(
+        &self,
+    ) -> Result<std::collections::HashMap<String, models::Balance>, String> {
+        let balances = self.clients()
+            .map(|(label, client)| async move {
+                let balance = client.get_trading_balance().await
+                    .map_err(|error| format!("{}: {}", label, error))?;
+                Ok::<_, String>((label.clone(), balance))
+            });
+        let balances = futures::future::try_join_all(balances).await?;
+        Ok(balances.into_iter().collect())
+    }
+}
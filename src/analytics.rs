@@ -0,0 +1,192 @@
+use std::str::FromStr;
+use super::base;
+use super::models;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum FillSize {
+    /// Base currency quantity.
+    Quantity(f64),
+    /// Quote currency amount to spend or receive.
+    QuoteAmount(f64),
+}
+
+#[derive(Clone, Debug)]
+pub struct FillSimulation {
+    pub quantity: f64,
+    pub quote_amount: f64,
+    /// Volume-weighted average fill price.
+    pub average_price: f64,
+    pub best_price: f64,
+    pub worst_price: f64,
+    /// Distance of the average price from the best price, positive when worse.
+    pub slippage_bps: f64,
+    pub levels_consumed: usize,
+    /// Fee in quote currency.
+    pub fee: f64,
+    /// Average price after the fee is paid.
+    pub effective_price: f64,
+    /// `false` when the page has not enough liquidity for the requested size.
+    pub complete: bool,
+}
+
+/// Fraction of the requested size below which the rest is treated as filled.
+const SIZE_TOLERANCE: f64 = 1e-12;
+
+/// Walks `page` from the best level like a taker order of `side` would.
+/// A buy consumes asks, so `page` must be built with `base::Side::Sell`,
+/// and a sell consumes bids built with `base::Side::Buy`.
+/// Returns `None` for an empty page or a non-positive size.
+pub fn simulate_fill(
+    page: &models::typed::OrderBookPage,
+    side: base::Side,
+    size: FillSize,
+    fee_rate: f64,
+) -> Option<FillSimulation> {
+    let best_price = page.prices.first()?.rate;
+    let mut remaining = match size {
+        FillSize::Quantity(quantity) => quantity,
+        FillSize::QuoteAmount(amount) => amount,
+    };
+    if remaining.is_nan() || remaining <= 0.0 {
+        return None;
+    }
+    // Rounding dust left in the unit of `size` that still counts as filled.
+    let tolerance = remaining * SIZE_TOLERANCE;
+    let mut quantity = 0.0;
+    let mut quote_amount = 0.0;
+    let mut worst_price = best_price;
+    let mut levels_consumed = 0;
+    for price in page.prices.iter() {
+        if remaining <= tolerance {
+            break;
+        }
+        let level_quantity = match size {
+            FillSize::Quantity(_) => price.amount.min(remaining),
+            FillSize::QuoteAmount(_) => price.amount.min(remaining / price.rate),
+        };
+        quantity += level_quantity;
+        quote_amount += level_quantity * price.rate;
+        remaining -= match size {
+            FillSize::Quantity(_) => level_quantity,
+            FillSize::QuoteAmount(_) => level_quantity * price.rate,
+        };
+        worst_price = price.rate;
+        levels_consumed += 1;
+    }
+    if quantity <= 0.0 {
+        return None;
+    }
+    let average_price = quote_amount / quantity;
+    let fee = quote_amount * fee_rate;
+    let (slippage_bps, effective_price) = match side {
+        base::Side::Buy => (
+            (average_price - best_price) / best_price * 10_000.0,
+            (quote_amount + fee) / quantity,
+        ),
+        base::Side::Sell => (
+            (best_price - average_price) / best_price * 10_000.0,
+            (quote_amount - fee) / quantity,
+        ),
+    };
+    Some(FillSimulation {
+        quantity,
+        quote_amount,
+        average_price,
+        best_price,
+        worst_price,
+        slippage_bps,
+        levels_consumed,
+        fee,
+        effective_price,
+        complete: remaining <= tolerance,
+    })
+}
+
+/// Fee rate paid by orders that take liquidity from the book.
+pub fn taker_fee_rate(commission: &models::TradingCommission) -> Result<f64, String> {
+    f64::from_str(&commission.take_liquidity_rate)
+        .map_err(|error| format!(
+            "Bad take liquidity rate {}: {:#?}",
+            commission.take_liquidity_rate,
+            error))
+}
+
+/// Fee rate paid by orders that rest in the book.
+pub fn maker_fee_rate(commission: &models::TradingCommission) -> Result<f64, String> {
+    f64::from_str(&commission.provide_liquidity_rate)
+        .map_err(|error| format!(
+            "Bad provide liquidity rate {}: {:#?}",
+            commission.provide_liquidity_rate,
+            error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coin;
+
+    fn page(levels: &[(f64, f64)]) -> models::typed::OrderBookPage {
+        models::typed::OrderBookPage {
+            symbol: coin::Symbol::new(coin::Coin::BTC, coin::Coin::USDT),
+            prices: levels.iter()
+                .map(|(rate, amount)| models::typed::Price { rate: *rate, amount: *amount })
+                .collect(),
+        }
+    }
+
+    fn close(left: f64, right: f64) -> bool {
+        (left - right).abs() < 1e-9
+    }
+
+    #[test]
+    fn buy_quantity_walks_levels() {
+        let asks = page(&[(100.0, 1.0), (101.0, 2.0), (102.0, 5.0)]);
+        let fill = simulate_fill(&asks, base::Side::Buy, FillSize::Quantity(2.0), 0.0).unwrap();
+        assert!(close(fill.quantity, 2.0));
+        assert!(close(fill.quote_amount, 201.0));
+        assert!(close(fill.average_price, 100.5));
+        assert!(close(fill.worst_price, 101.0));
+        assert!(close(fill.slippage_bps, 50.0));
+        assert_eq!(fill.levels_consumed, 2);
+        assert!(fill.complete);
+    }
+
+    #[test]
+    fn sell_quote_amount_walks_levels() {
+        let bids = page(&[(100.0, 1.0), (90.0, 10.0)]);
+        let fill = simulate_fill(&bids, base::Side::Sell, FillSize::QuoteAmount(190.0), 0.001).unwrap();
+        assert!(close(fill.quantity, 2.0));
+        assert!(close(fill.average_price, 95.0));
+        assert!(close(fill.slippage_bps, 500.0));
+        assert!(close(fill.fee, 0.19));
+        assert!(close(fill.effective_price, (190.0 - 0.19) / 2.0));
+        assert!(fill.complete);
+    }
+
+    #[test]
+    fn quote_amount_dust_does_not_consume_next_level() {
+        let asks = page(&[(3.7, 10.0), (4.0, 10.0)]);
+        let fill = simulate_fill(&asks, base::Side::Buy, FillSize::QuoteAmount(1.0), 0.0).unwrap();
+        assert_eq!(fill.levels_consumed, 1);
+        assert!(close(fill.worst_price, 3.7));
+        assert!(close(fill.slippage_bps, 0.0));
+        assert!(fill.complete);
+    }
+
+    #[test]
+    fn reports_incomplete_fill() {
+        let asks = page(&[(100.0, 1.0)]);
+        let fill = simulate_fill(&asks, base::Side::Buy, FillSize::Quantity(3.0), 0.0).unwrap();
+        assert!(close(fill.quantity, 1.0));
+        assert!(close(fill.slippage_bps, 0.0));
+        assert!(!fill.complete);
+    }
+
+    #[test]
+    fn rejects_empty_page_and_bad_size() {
+        assert!(simulate_fill(&page(&[]), base::Side::Buy, FillSize::Quantity(1.0), 0.0).is_none());
+        let asks = page(&[(100.0, 1.0)]);
+        assert!(simulate_fill(&asks, base::Side::Buy, FillSize::Quantity(0.0), 0.0).is_none());
+        assert!(simulate_fill(&asks, base::Side::Buy, FillSize::Quantity(f64::NAN), 0.0).is_none());
+    }
+}
@@ -31,3 +31,52 @@ impl Type {
         }
     }
 }
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ContingencyType {
+    /// All orders are executed in a single transaction or none is.
+    AllOrNone,
+    /// Execution of one order cancels the other.
+    OneCancelOther,
+    /// The second order is placed once the first one is filled.
+    OneTriggerOther,
+    /// Two orders canceling each other are placed once the first one is filled.
+    OneTriggerOneCancelOther,
+}
+
+impl ContingencyType {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            ContingencyType::AllOrNone => "allOrNone",
+            ContingencyType::OneCancelOther => "oneCancelOther",
+            ContingencyType::OneTriggerOther => "oneTriggerOther",
+            ContingencyType::OneTriggerOneCancelOther => "oneTriggerOneCancelOther",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Market {
+    Spot,
+    Margin,
+    Futures,
+}
+
+impl Market {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            Market::Spot => "spot",
+            Market::Margin => "margin",
+            Market::Futures => "futures",
+        }
+    }
+
+    /// Path segment that prefixes trading endpoints of the market.
+    pub fn path(self) -> Option<&'static str> {
+        match self {
+            Market::Spot => None,
+            Market::Margin => Some("margin"),
+            Market::Futures => Some("futures"),
+        }
+    }
+}
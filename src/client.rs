@@ -1,14 +1,32 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+use super::base;
 use super::coin;
 use super::context;
 use super::extractor;
 use super::models;
+use super::order;
+use super::pre_trade;
+use super::public_client;
+use super::symbol_registry;
+use super::transaction;
+use futures::TryStreamExt;
 
-pub const BAD_URL: &'static str = "Unable to modify path of url";
-pub const BAD_BODY: &'static str = "Bad body. Failed to get bytes.";
+pub const BAD_URL: &str = "Unable to modify path of url";
+pub const BAD_BODY: &str = "Bad body. Failed to get bytes.";
+
+/// Longest `wait` accepted by the order endpoint.
+const MAX_ORDER_WAIT: std::time::Duration = std::time::Duration::from_secs(60);
+const ORDER_WAIT_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+const ORDER_WAIT_RETRY: std::time::Duration = std::time::Duration::from_secs(1);
+/// Concurrent requests of `BTCClient::estimate_withdrawal_fees`.
+const MAX_FEE_ESTIMATES: usize = 4;
 
 pub struct BTCClient<TConnector> {
     client: std::sync::Arc<hyper::Client<TConnector>>,
     auth_context: std::sync::Arc<context::AuthContext>,
+    symbol_registry: Option<std::sync::Arc<symbol_registry::SymbolRegistry>>,
+    pre_trade_validator: Option<std::sync::Arc<pre_trade::PreTradeValidator>>,
 }
 
 impl<TConnector> BTCClient<TConnector>
@@ -20,6 +38,25 @@ where
     const FEE: &'static str = "fee";
     const BALANCE: &'static str = "balance";
     const ORDER: &'static str = "order";
+    const CRYPTO: &'static str = "crypto";
+    const ADDRESS: &'static str = "address";
+    const ADDRESSES: &'static str = "addresses";
+    const WITHDRAW: &'static str = "withdraw";
+    const ESTIMATE_WITHDRAW: &'static str = "estimate-withdraw";
+    const TRANSACTIONS: &'static str = "transactions";
+    const CHECK_ADDRESS: &'static str = "check-address";
+    const IS_MINE: &'static str = "is-mine";
+    const CONVERT: &'static str = "convert";
+    const PAIRS: &'static str = "pairs";
+    const QUOTE: &'static str = "quote";
+    const TRANSFER: &'static str = "transfer";
+    const INTERNAL: &'static str = "internal";
+    const SUB_ACCOUNT: &'static str = "sub-acc";
+    const FREEZE: &'static str = "freeze";
+    const ACTIVATE: &'static str = "activate";
+    const ACL: &'static str = "acl";
+    const POSITION: &'static str = "position";
+    const LIST: &'static str = "list";
 
     pub fn new(
         client: std::sync::Arc<hyper::Client<TConnector>>,
@@ -28,9 +65,29 @@ where
         BTCClient {
             client,
             auth_context,
+            symbol_registry: None,
+            pre_trade_validator: None,
         }
     }
 
+    /// Rounds quantity and price of every created order to the symbol increments.
+    pub fn with_symbol_registry(
+        mut self,
+        symbol_registry: std::sync::Arc<symbol_registry::SymbolRegistry>,
+    ) -> BTCClient<TConnector> {
+        self.symbol_registry = Some(symbol_registry);
+        self
+    }
+
+    /// Rejects created orders that fail validation, after rounding if a symbol registry is set.
+    pub fn with_pre_trade_validator(
+        mut self,
+        pre_trade_validator: std::sync::Arc<pre_trade::PreTradeValidator>,
+    ) -> BTCClient<TConnector> {
+        self.pre_trade_validator = Some(pre_trade_validator);
+        self
+    }
+
     pub async fn get_account_balance(&self) -> Result<models::Balance, String> {
         let mut url = self.auth_context.base_url.clone();
         url.path_segments_mut()
@@ -69,21 +126,7 @@ where
         &self,
         coins: Option<coin::Symbol>
     ) -> Result<models::Orders, String> {
-        let mut url = self.auth_context.base_url.clone();
-        url.path_segments_mut().expect(BAD_URL).push(Self::ORDER);
-        if let Some(coins) = coins {
-            url.query_pairs_mut()
-                .append_pair("symbol", &coins.to_string());
-        }
-        let (_header, body) = process_with_empty_body(
-            &self.client,
-            &self.auth_context,
-            url,
-            hyper::Method::GET).await?;
-        match extractor::extract_orders(body).await {
-            Some(orders) => Ok(orders),
-            None => Err("Deserialization error".to_owned()),
-        }
+        self.get_active_orders_in(base::Market::Spot, coins).await
     }
 
     pub async fn get_order_by_id(
@@ -91,6 +134,15 @@ where
         id: &str,
         wait: Option<u64>,
     ) -> Result<models::Order, String> {
+        self.request_order(id, wait).await?.1
+    }
+
+    /// Response status with the order, the outer error is a transport failure.
+    async fn request_order(
+        &self,
+        id: &str,
+        wait: Option<u64>,
+    ) -> Result<(hyper::StatusCode, Result<models::Order, String>), String> {
         let mut url = self.auth_context.base_url.clone();
         url.path_segments_mut()
             .expect(BAD_URL)
@@ -100,27 +152,205 @@ where
             url.query_pairs_mut()
                 .append_pair("wait", &format!("{}", wait));
         }
+        let (header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        let order = if header.status == hyper::StatusCode::OK {
+            match extractor::extract_order(body).await {
+                Some(order) => Ok(order),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(body).await;
+            Err(format!("Error on getting order {}: {:#?}", id, error))
+        };
+        Ok((header.status, order))
+    }
+
+    /// Long-polls the order until it reaches a final status. Polls returning an open
+    /// order or failing are retried after `ORDER_WAIT_RETRY` until `deadline`, after
+    /// which the last error is returned. Client errors other than timeouts and rate
+    /// limits are returned at once.
+    pub async fn wait_for_order(
+        &self,
+        id: &str,
+        deadline: std::time::Instant,
+    ) -> Result<order::TrackedOrder, String> {
+        let mut last_error = None;
+        loop {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            if remaining.is_zero() {
+                return Err(match last_error {
+                    Some(error) => format!("Timed out waiting for order {}: {}", id, error),
+                    None => format!("Timed out waiting for order {}", id),
+                });
+            }
+            let wait = remaining.min(MAX_ORDER_WAIT);
+            let request = self.request_order(id, Some(wait.as_millis() as u64));
+            match tokio::time::timeout(wait + ORDER_WAIT_GRACE, request).await {
+                Ok(Ok((_, Ok(current)))) => {
+                    let status = order::OrderStatus::from_str(&current.status)?;
+                    if status.is_final() {
+                        return order::TrackedOrder::new(&current);
+                    }
+                    let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                    tokio::time::sleep(remaining.min(ORDER_WAIT_RETRY)).await;
+                },
+                Ok(Ok((status, Err(error)))) if is_permanent(status) => return Err(error),
+                Ok(Ok((_, Err(error)))) | Ok(Err(error)) => {
+                    last_error = Some(error);
+                    let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                    tokio::time::sleep(remaining.min(ORDER_WAIT_RETRY)).await;
+                },
+                Err(_) => last_error = Some("Request timed out".to_owned()),
+            }
+        }
+    }
+
+    pub async fn create_market_order(
+        &self,
+        order: models::typed::CreateMarketOrder,
+    ) -> Result<models::Order, String> {
+        self.create_market_order_in(base::Market::Spot, order).await
+    }
+
+    pub async fn create_limit_order(
+        &self,
+        order: models::typed::CreateLimitOrder,
+    ) -> Result<models::Order, String> {
+        self.create_limit_order_in(base::Market::Spot, order).await
+    }
+
+    pub async fn cancel_all_orders(
+        &self,
+        symbol: Option<coin::Symbol>,
+    ) -> Result<models::Orders, String> {
+        self.cancel_all_orders_in(base::Market::Spot, symbol).await
+    }
+    
+    pub async fn cancel_order_by_id(
+        &self,
+        id: &str
+    ) -> Result<models::Order, String> {
+        self.cancel_order_by_id_in(base::Market::Spot, id).await
+    }
+
+    pub async fn get_active_orders_in(
+        &self,
+        market: base::Market,
+        symbol: Option<coin::Symbol>,
+    ) -> Result<models::Orders, String> {
+        let mut url = self.market_url(market);
+        url.path_segments_mut().expect(BAD_URL).push(Self::ORDER);
+        if let Some(symbol) = symbol {
+            url.query_pairs_mut()
+                .append_pair("symbol", &symbol.to_string());
+        }
         let (_header, body) = process_with_empty_body(
             &self.client,
             &self.auth_context,
             url,
             hyper::Method::GET).await?;
-        match extractor::extract_order(body).await {
-            Some(order) => Ok(order),
+        match extractor::extract_orders(body).await {
+            Some(orders) => Ok(orders),
             None => Err("Deserialization error".to_owned()),
         }
     }
 
-    pub async fn create_market_order(
+    pub async fn create_market_order_in(
         &self,
+        market: base::Market,
         order: models::typed::CreateMarketOrder,
     ) -> Result<models::Order, String> {
-        let mut url = self.auth_context.base_url.clone();
+        let order = match &self.symbol_registry {
+            Some(symbol_registry) => symbol_registry.round_market_order(order)?,
+            None => order,
+        };
+        if let Some(validator) = &self.pre_trade_validator {
+            let context = self.get_market_context(
+                market,
+                order.symbol(),
+                validator.needs_balance(),
+                validator.needs_mid_price(None)).await?;
+            validator.validate_market_order(&order, &context).into_result()?;
+        }
+        let body = serde_json::to_vec(&order.to_model())
+            .expect("Failed to serialize CreateMarketOrder");
+        self.create_order_in(market, body).await
+    }
+
+    pub async fn create_limit_order_in(
+        &self,
+        market: base::Market,
+        order: models::typed::CreateLimitOrder,
+    ) -> Result<models::Order, String> {
+        let order = match &self.symbol_registry {
+            Some(symbol_registry) => symbol_registry.round_limit_order(order)?,
+            None => order,
+        };
+        if let Some(validator) = &self.pre_trade_validator {
+            let context = self.get_market_context(
+                market,
+                order.symbol(),
+                validator.needs_balance(),
+                validator.needs_mid_price(Some(order.price()))).await?;
+            validator.validate_limit_order(&order, &context).into_result()?;
+        }
+        let body = serde_json::to_vec(&order.to_model())
+            .expect("Failed to serialize CreateLimitOrder");
+        self.create_order_in(market, body).await
+    }
+
+    /// Balance of the market account and the current mid price of `symbol`, each only if requested.
+    pub async fn get_market_context(
+        &self,
+        market: base::Market,
+        symbol: &coin::Symbol,
+        with_balance: bool,
+        with_mid_price: bool,
+    ) -> Result<pre_trade::MarketContext, String> {
+        let balance = match market {
+            _ if !with_balance => None,
+            base::Market::Spot => Some(self.get_trading_balance().await?),
+            base::Market::Futures => Some(self.get_futures_balance().await?),
+            base::Market::Margin => None,
+        };
+        let mid_price = if with_mid_price {
+            self.get_mid_price(symbol).await?
+        } else {
+            None
+        };
+        Ok(pre_trade::MarketContext {
+            balance,
+            mid_price,
+        })
+    }
+
+    /// Middle of the best ask and bid of `symbol`, `None` if a side of the book is empty.
+    pub async fn get_mid_price(&self, symbol: &coin::Symbol) -> Result<Option<f64>, String> {
+        let orderbook = public_client::PublicClient::new(
+            self.client.clone(),
+            self.auth_context.base_url.clone())
+            .get_symbol_from_orderbook(symbol.clone(), Some(1), None).await?;
+        let best = |prices: &models::Prices| prices.first()
+            .and_then(|price| f64::from_str(&price.price).ok());
+        match (best(&orderbook.ask), best(&orderbook.bid)) {
+            (Some(ask), Some(bid)) => Ok(Some((ask + bid) / 2.0)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn create_order_in(
+        &self,
+        market: base::Market,
+        body: Vec<u8>,
+    ) -> Result<models::Order, String> {
+        let mut url = self.market_url(market);
         url.path_segments_mut()
             .expect(BAD_URL)
             .push(Self::ORDER);
-        let body = serde_json::to_vec(&order.to_model())
-            .expect("Failed to serialize CreateMarketOrder");
         let (header, response_body) = process(
             &self.client,
             &self.auth_context,
@@ -138,38 +368,43 @@ where
         }
     }
 
-    pub async fn create_limit_order(
+    /// Order lists exist only in API v3, the request goes to `spot/order/list`
+    /// next to the version segment of the base URL, signed with the v3 scheme.
+    pub async fn create_order_list(
         &self,
-        order: models::typed::CreateLimitOrder,
-    ) -> Result<models::Order, String> {
-        let mut url = self.auth_context.base_url.clone();
+        list: models::typed::CreateOrderList,
+    ) -> Result<models::typed::OrderList, String> {
+        let mut url = self.v3_url()?;
         url.path_segments_mut()
             .expect(BAD_URL)
-            .push(Self::ORDER);
-        let body = serde_json::to_vec(&order.to_model())
-            .expect("Failed to serialize CreateLimitOrder");
-        let (header, response_body) = process(
+            .push(base::Market::Spot.to_string())
+            .push(Self::ORDER)
+            .push(Self::LIST);
+        let body = serde_json::to_vec(&list.to_model())
+            .expect("Failed to serialize CreateOrderList");
+        let (header, response_body) = process_v3(
             &self.client,
             &self.auth_context,
             url,
             hyper::Method::POST,
             body).await?;
-        if header.status == hyper::StatusCode::OK{
-            match extractor::extract_order(response_body).await {
-                Some(order) => Ok(order),
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_spot_orders(response_body).await {
+                Some(orders) => models::typed::OrderList::new(orders),
                 None => Err("Deserialization error".to_owned()),
             }
         } else {
             let error = extractor::extract_error(response_body).await;
-            Err(format!("Error on creating order: {:#?}", error))
+            Err(format!("Error on creating order list: {:#?}", error))
         }
     }
 
-    pub async fn cancel_all_orders(
+    pub async fn cancel_all_orders_in(
         &self,
+        market: base::Market,
         symbol: Option<coin::Symbol>,
     ) -> Result<models::Orders, String> {
-        let mut url = self.auth_context.base_url.clone();
+        let mut url = self.market_url(market);
         url.path_segments_mut()
             .expect(BAD_URL)
             .push(Self::ORDER);
@@ -187,12 +422,13 @@ where
             None => Err("Deserialization error".to_owned()),
         }
     }
-    
-    pub async fn cancel_order_by_id(
+
+    pub async fn cancel_order_by_id_in(
         &self,
-        id: &str
+        market: base::Market,
+        id: &str,
     ) -> Result<models::Order, String> {
-        let mut url = self.auth_context.base_url.clone();
+        let mut url = self.market_url(market);
         url.path_segments_mut()
             .expect(BAD_URL)
             .push(Self::ORDER)
@@ -202,12 +438,143 @@ where
             &self.auth_context,
             url,
             hyper::Method::DELETE).await?;
-       match extractor::extract_order(body).await {
+        match extractor::extract_order(body).await {
             Some(order) => Ok(order),
             None => Err("Deserialization error".to_owned()),
         }
     }
 
+    async fn get_accounts_in(
+        &self,
+        market: base::Market,
+    ) -> Result<models::IsolatedAccounts, String> {
+        let mut url = self.market_url(market);
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT);
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_isolated_accounts(body).await {
+            Some(accounts) => Ok(accounts),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    async fn get_account_in(
+        &self,
+        market: base::Market,
+        symbol: coin::Symbol,
+    ) -> Result<models::IsolatedAccount, String> {
+        let mut url = self.market_url(market);
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(&symbol.to_string());
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_isolated_account(body).await {
+            Some(account) => Ok(account),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    async fn close_account_in(
+        &self,
+        market: base::Market,
+        symbol: coin::Symbol,
+    ) -> Result<models::IsolatedAccount, String> {
+        let mut url = self.market_url(market);
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(&symbol.to_string());
+        let (header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::DELETE).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_isolated_account(body).await {
+                Some(account) => Ok(account),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(body).await;
+            Err(format!("Error on closing account: {:#?}", error))
+        }
+    }
+
+    async fn get_positions_in(
+        &self,
+        market: base::Market,
+    ) -> Result<models::Positions, String> {
+        let mut url = self.market_url(market);
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::POSITION);
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_positions(body).await {
+            Some(positions) => Ok(positions),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    async fn close_position_in(
+        &self,
+        market: base::Market,
+        symbol: coin::Symbol,
+        price: Option<f64>,
+    ) -> Result<models::Order, String> {
+        let mut url = self.market_url(market);
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::POSITION)
+            .push(&symbol.to_string());
+        if let Some(price) = price {
+            url.query_pairs_mut()
+                .append_pair("price", &format!("{}", price));
+        }
+        let (header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::DELETE).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_order(body).await {
+                Some(order) => Ok(order),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(body).await;
+            Err(format!("Error on closing position: {:#?}", error))
+        }
+    }
+
+    /// Base URL with the API version segment, e.g. `api/2`, replaced by `3`.
+    fn v3_url(&self) -> Result<url::Url, String> {
+        v3_url(&self.auth_context.base_url)
+    }
+
+    fn market_url(&self, market: base::Market) -> url::Url {
+        let mut url = self.auth_context.base_url.clone();
+        if let Some(prefix) = market.path() {
+            url.path_segments_mut()
+                .expect(BAD_URL)
+                .push(prefix);
+        }
+        url
+    }
+
     pub async fn get_trading_commission(
         &self,
         symbol: coin::Symbol,
@@ -228,45 +595,895 @@ where
             None => Err("Deserialization error".to_owned()),
         }
     }
-}
-
-fn get_message(
-    method: hyper::Method,
-    timestamp: &str,
-    path_with_query: &str,
-    body: &str,
-) -> String {
-    format!("{}{}{}{}", method, timestamp, path_with_query, body)
-}
 
-async fn process_with_empty_body<TConnector>(
-    client: &hyper::Client<TConnector>,
-    auth_context: &context::AuthContext,
-    url: url::Url,
-    method: hyper::Method,
-) -> Result<(http::response::Parts, hyper::Body), String>
-where
-    TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
-{
-    let body = hyper::Body::empty();
-    let body = hyper::body::to_bytes(body).await.expect(BAD_BODY);
-    let body = String::from_utf8(body.to_vec()).expect("Body must be valid UTF-8");
-    let timestamp = chrono::Utc::now().timestamp().to_string();
-    let path_with_query = &url[url::Position::BeforePath..];
-    let message = get_message(method.clone(), &timestamp, path_with_query, &body);
-    let jwt = auth_context.sign(message, timestamp);
-    let request = match hyper::Request::builder()
-        .header("Accept", "application/json")
-        .header("Authorization", jwt)
-        .uri(url.to_string())
-        .method(method)
-        .body(hyper::Body::empty()) {
-        Ok(request) => request,
-        Err(error) => return Err(format!("Failed create request: {:#?}", error)),
-    };
-    match client.request(request).await {
-        Ok(response) => Ok(response.into_parts()),
-        Err(error) => Err(format!("Failed to craete request: {:#?}", error)),
+    /// Checked with `models::typed::DepositAddress::new`.
+    pub async fn get_deposit_address(
+        &self,
+        currency: &models::PublicCurrency,
+    ) -> Result<models::typed::DepositAddress, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CRYPTO)
+            .push(Self::ADDRESS)
+            .push(&currency.id);
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_deposit_address(body).await {
+            Some(address) => models::typed::DepositAddress::new(address, currency),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    /// Checked with `models::typed::DepositAddress::new`.
+    pub async fn create_deposit_address(
+        &self,
+        currency: &models::PublicCurrency,
+    ) -> Result<models::typed::DepositAddress, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CRYPTO)
+            .push(Self::ADDRESS)
+            .push(&currency.id);
+        let (header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::POST).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_deposit_address(body).await {
+                Some(address) => models::typed::DepositAddress::new(address, currency),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(body).await;
+            Err(format!("Error on creating deposit address: {:#?}", error))
+        }
+    }
+
+    /// Every address is checked with `models::typed::DepositAddress::new`.
+    pub async fn get_deposit_addresses(
+        &self,
+        currency: &models::PublicCurrency,
+    ) -> Result<Vec<models::typed::DepositAddress>, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CRYPTO)
+            .push(Self::ADDRESSES)
+            .push(&currency.id);
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_deposit_addresses(body).await {
+            Some(addresses) => addresses.into_iter()
+                .map(|address| models::typed::DepositAddress::new(address, currency))
+                .collect(),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    pub async fn create_withdrawal(
+        &self,
+        withdrawal: models::typed::CreateWithdrawal,
+        currency: Option<&models::PublicCurrency>,
+    ) -> Result<models::Withdrawal, String> {
+        if let Some(currency) = currency {
+            withdrawal.validate(currency)?;
+        }
+        let check_address = withdrawal.check_address();
+        let withdrawal = withdrawal.to_model();
+        if check_address {
+            let currency = coin::Coin::from(withdrawal.currency.as_str());
+            if !self.check_address(currency, &withdrawal.address).await? {
+                return Err(format!(
+                    "Address {} is not valid for {}",
+                    withdrawal.address,
+                    withdrawal.currency));
+            }
+        }
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CRYPTO)
+            .push(Self::WITHDRAW);
+        let body = serde_json::to_vec(&withdrawal)
+            .expect("Failed to serialize CreateWithdrawal");
+        let (header, response_body) = process(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::POST,
+            body).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_withdrawal(response_body).await {
+                Some(withdrawal) => Ok(withdrawal),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(response_body).await;
+            Err(format!("Error on creating withdrawal: {:#?}", error))
+        }
+    }
+
+    pub async fn commit_withdrawal(&self, id: &str) -> Result<bool, String> {
+        self.finish_withdrawal(id, hyper::Method::PUT).await
+    }
+
+    pub async fn rollback_withdrawal(&self, id: &str) -> Result<bool, String> {
+        self.finish_withdrawal(id, hyper::Method::DELETE).await
+    }
+
+    async fn finish_withdrawal(
+        &self,
+        id: &str,
+        method: hyper::Method,
+    ) -> Result<bool, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CRYPTO)
+            .push(Self::WITHDRAW)
+            .push(id);
+        let (header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            method).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_result(body).await {
+                Some(result) => Ok(result.result),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(body).await;
+            Err(format!("Error on finishing withdrawal {}: {:#?}", id, error))
+        }
+    }
+
+    pub async fn estimate_withdrawal_fee(
+        &self,
+        currency: coin::Coin,
+        amount: rust_decimal::Decimal,
+    ) -> Result<models::typed::WithdrawalFee, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CRYPTO)
+            .push(Self::ESTIMATE_WITHDRAW);
+        url.query_pairs_mut()
+            .append_pair("currency", currency.id())
+            .append_pair("amount", &amount.to_string());
+        let (header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_withdrawal_fee(body).await {
+                Some(fee) => models::typed::WithdrawalFee::new(currency, amount, fee),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(body).await;
+            Err(format!("Error on estimating withdrawal fee of {}: {:#?}", currency, error))
+        }
+    }
+
+    /// The exchange estimates one currency per request, so at most
+    /// `MAX_FEE_ESTIMATES` requests run at a time. Results keep the request order.
+    pub async fn estimate_withdrawal_fees(
+        &self,
+        requests: Vec<(coin::Coin, rust_decimal::Decimal)>,
+    ) -> Vec<(coin::Coin, Result<models::typed::WithdrawalFee, String>)> {
+        use futures::StreamExt;
+        futures::stream::iter(requests)
+            .map(|(currency, amount)| async move {
+                let fee = self.estimate_withdrawal_fee(currency.clone(), amount).await;
+                (currency, fee)
+            })
+            .buffered(MAX_FEE_ESTIMATES)
+            .collect()
+            .await
+    }
+
+    /// Estimates payout fees for every non-empty balance and keeps the ones
+    /// worth withdrawing net of fees. Currencies whose fee could not be
+    /// estimated are returned separately with the error.
+    pub async fn get_withdrawable_balances(
+        &self,
+        balance: &models::Balance,
+        min_net_amount: rust_decimal::Decimal,
+    ) -> (Vec<models::typed::WithdrawalFee>, Vec<(coin::Coin, String)>) {
+        let requests = balance.iter()
+            .filter_map(|currency| {
+                let available = rust_decimal::Decimal::from_str(&currency.available).ok()?;
+                if available > rust_decimal::Decimal::ZERO {
+                    Some((coin::Coin::from(currency.currency.as_str()), available))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let mut fees = Vec::new();
+        let mut errors = Vec::new();
+        for (currency, fee) in self.estimate_withdrawal_fees(requests).await {
+            match fee {
+                Ok(fee) => fees.push(fee),
+                Err(error) => errors.push((currency, error)),
+            }
+        }
+        (models::typed::worth_withdrawing(balance, &fees, min_net_amount), errors)
+    }
+
+    /// Checks that `address` is a well-formed address for `currency`.
+    pub async fn check_address(
+        &self,
+        currency: coin::Coin,
+        address: &str,
+    ) -> Result<bool, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CRYPTO)
+            .push(Self::CHECK_ADDRESS);
+        url.query_pairs_mut()
+            .append_pair("currency", &currency.to_string())
+            .append_pair("address", address);
+        let (header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_result(body).await {
+                Some(result) => Ok(result.result),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(body).await;
+            Err(format!("Error on checking address {}: {:#?}", address, error))
+        }
+    }
+
+    /// Checks whether `address` is one of the deposit addresses of this account.
+    pub async fn is_address_mine(&self, address: &str) -> Result<bool, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CRYPTO)
+            .push(Self::IS_MINE)
+            .push(address);
+        let (header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_result(body).await {
+                Some(result) => Ok(result.result),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(body).await;
+            Err(format!("Error on checking address {}: {:#?}", address, error))
+        }
+    }
+
+    pub async fn get_convert_pairs(&self) -> Result<Vec<models::typed::ConvertPair>, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CONVERT)
+            .push(Self::PAIRS);
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_convert_pairs(body).await {
+            Some(pairs) => pairs.into_iter()
+                .map(models::typed::ConvertPair::try_from)
+                .collect(),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    /// `amount` is denominated in `from`.
+    pub async fn get_convert_quote(
+        &self,
+        from: coin::Coin,
+        to: coin::Coin,
+        amount: rust_decimal::Decimal,
+    ) -> Result<models::typed::ConvertQuote, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CONVERT)
+            .push(Self::QUOTE);
+        let quote = models::CreateConvertQuote {
+            from_currency: from.to_string(),
+            to_currency: to.to_string(),
+            amount: amount.normalize().to_string(),
+        };
+        let body = serde_json::to_vec(&quote)
+            .expect("Failed to serialize CreateConvertQuote");
+        let (header, response_body) = process(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::POST,
+            body).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_convert_quote(response_body).await {
+                Some(quote) => models::typed::ConvertQuote::try_from(quote),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(response_body).await;
+            Err(format!("Error on requesting convert quote: {:#?}", error))
+        }
+    }
+
+    pub async fn execute_convert(
+        &self,
+        quote_id: &str,
+    ) -> Result<models::Transaction, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::CONVERT);
+        let execute = models::ExecuteConvert {
+            quote_id: quote_id.to_owned(),
+        };
+        let body = serde_json::to_vec(&execute)
+            .expect("Failed to serialize ExecuteConvert");
+        let (header, response_body) = process(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::POST,
+            body).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_transaction(response_body).await {
+                Some(transaction) => Ok(transaction),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(response_body).await;
+            Err(format!("Error on executing convert {}: {:#?}", quote_id, error))
+        }
+    }
+
+    /// Moves funds to another exchange user without touching the blockchain.
+    pub async fn create_internal_transfer(
+        &self,
+        transfer: models::typed::CreateInternalTransfer,
+    ) -> Result<models::InternalTransfer, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::TRANSFER)
+            .push(Self::INTERNAL);
+        let recipient = transfer.recipient().clone();
+        let body = serde_json::to_vec(&transfer.to_model())
+            .expect("Failed to serialize CreateInternalTransfer");
+        let (header, response_body) = process(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::POST,
+            body).await?;
+        match header.status {
+            hyper::StatusCode::OK => match extractor::extract_internal_transfer(response_body).await {
+                Some(transfer) => Ok(transfer),
+                None => Err("Deserialization error".to_owned()),
+            },
+            hyper::StatusCode::NOT_FOUND => {
+                let error = extractor::extract_error(response_body).await;
+                Err(format!(
+                    "Transfer recipient {:?} cannot be resolved: {:#?}",
+                    recipient,
+                    error))
+            },
+            _ => {
+                let error = extractor::extract_error(response_body).await;
+                Err(format!("Error on creating internal transfer: {:#?}", error))
+            },
+        }
+    }
+
+    pub async fn get_sub_accounts(&self) -> Result<models::SubAccounts, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::SUB_ACCOUNT);
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_sub_accounts(body).await {
+            Some(sub_accounts) => Ok(sub_accounts.result),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    pub async fn freeze_sub_accounts(&self, ids: Vec<u64>) -> Result<bool, String> {
+        self.change_sub_accounts_state(Self::FREEZE, ids).await
+    }
+
+    pub async fn activate_sub_accounts(&self, ids: Vec<u64>) -> Result<bool, String> {
+        self.change_sub_accounts_state(Self::ACTIVATE, ids).await
+    }
+
+    async fn change_sub_accounts_state(
+        &self,
+        action: &str,
+        ids: Vec<u64>,
+    ) -> Result<bool, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::SUB_ACCOUNT)
+            .push(action);
+        let body = serde_json::to_vec(&models::SubAccountIds { ids })
+            .expect("Failed to serialize SubAccountIds");
+        let (header, response_body) = process(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::POST,
+            body).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_result(response_body).await {
+                Some(result) => Ok(result.result),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(response_body).await;
+            Err(format!("Error on {} sub-accounts: {:#?}", action, error))
+        }
+    }
+
+    pub async fn create_sub_account_transfer(
+        &self,
+        transfer: models::typed::CreateSubAccountTransfer,
+    ) -> Result<models::SubAccountTransfer, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::SUB_ACCOUNT)
+            .push(Self::TRANSFER);
+        let body = serde_json::to_vec(&transfer.to_model())
+            .expect("Failed to serialize CreateSubAccountTransfer");
+        let (header, response_body) = process(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::POST,
+            body).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_sub_account_transfer(response_body).await {
+                Some(transfer) => Ok(transfer),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(response_body).await;
+            Err(format!("Error on sub-account transfer: {:#?}", error))
+        }
+    }
+
+    pub async fn get_sub_account_balance(
+        &self,
+        sub_account_id: u64,
+    ) -> Result<models::SubAccountBalance, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::SUB_ACCOUNT)
+            .push(Self::BALANCE)
+            .push(&format!("{}", sub_account_id));
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_sub_account_balance(body).await {
+            Some(balance) => Ok(balance.result),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    pub async fn get_sub_account_acls(&self) -> Result<models::SubAccountAcls, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::SUB_ACCOUNT)
+            .push(Self::ACL);
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_sub_account_acls(body).await {
+            Some(acls) => Ok(acls.result),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    pub async fn update_sub_account_acl(
+        &self,
+        sub_account_ids: Vec<u64>,
+        acl: models::UpdateSubAccountAcl,
+    ) -> Result<models::SubAccountAcls, String> {
+        let ids = sub_account_ids.iter()
+            .map(|id| format!("{}", id))
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::SUB_ACCOUNT)
+            .push(Self::ACL)
+            .push(&ids);
+        let body = serde_json::to_vec(&acl)
+            .expect("Failed to serialize UpdateSubAccountAcl");
+        let (header, response_body) = process(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::PUT,
+            body).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_sub_account_acls(response_body).await {
+                Some(acls) => Ok(acls.result),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(response_body).await;
+            Err(format!("Error on updating sub-account ACL: {:#?}", error))
+        }
+    }
+
+    pub async fn get_margin_accounts(&self) -> Result<models::IsolatedAccounts, String> {
+        self.get_accounts_in(base::Market::Margin).await
+    }
+
+    pub async fn get_margin_account(
+        &self,
+        symbol: coin::Symbol,
+    ) -> Result<models::IsolatedAccount, String> {
+        self.get_account_in(base::Market::Margin, symbol).await
+    }
+
+    /// Creates the isolated margin account for `symbol` or changes its leverage and margin balance.
+    pub async fn update_margin_account(
+        &self,
+        symbol: coin::Symbol,
+        account: models::typed::UpdateMarginAccount,
+    ) -> Result<models::IsolatedAccount, String> {
+        let mut url = self.market_url(base::Market::Margin);
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(&symbol.to_string());
+        let body = serde_json::to_vec(&account.to_model())
+            .expect("Failed to serialize UpdateMarginAccount");
+        let (header, response_body) = process(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::PUT,
+            body).await?;
+        if header.status == hyper::StatusCode::OK {
+            match extractor::extract_isolated_account(response_body).await {
+                Some(account) => Ok(account),
+                None => Err("Deserialization error".to_owned()),
+            }
+        } else {
+            let error = extractor::extract_error(response_body).await;
+            Err(format!("Error on updating margin account: {:#?}", error))
+        }
+    }
+
+    /// Closes the position, cancels the orders and returns the margin balance of the account.
+    pub async fn close_margin_account(
+        &self,
+        symbol: coin::Symbol,
+    ) -> Result<models::IsolatedAccount, String> {
+        self.close_account_in(base::Market::Margin, symbol).await
+    }
+
+    pub async fn get_margin_positions(&self) -> Result<models::Positions, String> {
+        self.get_positions_in(base::Market::Margin).await
+    }
+
+    /// Closes the position by market or, when `price` is set, by a limit order.
+    pub async fn close_margin_position(
+        &self,
+        symbol: coin::Symbol,
+        price: Option<f64>,
+    ) -> Result<models::Order, String> {
+        self.close_position_in(base::Market::Margin, symbol, price).await
+    }
+
+    pub async fn get_active_margin_orders(
+        &self,
+        symbol: Option<coin::Symbol>,
+    ) -> Result<models::Orders, String> {
+        self.get_active_orders_in(base::Market::Margin, symbol).await
+    }
+
+    pub async fn create_margin_market_order(
+        &self,
+        order: models::typed::CreateMarketOrder,
+    ) -> Result<models::Order, String> {
+        self.create_market_order_in(base::Market::Margin, order).await
+    }
+
+    pub async fn create_margin_limit_order(
+        &self,
+        order: models::typed::CreateLimitOrder,
+    ) -> Result<models::Order, String> {
+        self.create_limit_order_in(base::Market::Margin, order).await
+    }
+
+    pub async fn cancel_all_margin_orders(
+        &self,
+        symbol: Option<coin::Symbol>,
+    ) -> Result<models::Orders, String> {
+        self.cancel_all_orders_in(base::Market::Margin, symbol).await
+    }
+
+    pub async fn cancel_margin_order_by_id(
+        &self,
+        id: &str,
+    ) -> Result<models::Order, String> {
+        self.cancel_order_by_id_in(base::Market::Margin, id).await
+    }
+
+    pub async fn get_futures_accounts(&self) -> Result<models::IsolatedAccounts, String> {
+        self.get_accounts_in(base::Market::Futures).await
+    }
+
+    pub async fn get_futures_account(
+        &self,
+        symbol: coin::Symbol,
+    ) -> Result<models::IsolatedAccount, String> {
+        self.get_account_in(base::Market::Futures, symbol).await
+    }
+
+    pub async fn get_futures_balance(&self) -> Result<models::Balance, String> {
+        let mut url = self.market_url(base::Market::Futures);
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::BALANCE);
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_balance(body).await {
+            Some(balance) => Ok(balance),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+
+    pub async fn get_futures_positions(&self) -> Result<models::Positions, String> {
+        self.get_positions_in(base::Market::Futures).await
+    }
+
+    /// Closes the position by market or, when `price` is set, by a limit order.
+    pub async fn close_futures_position(
+        &self,
+        symbol: coin::Symbol,
+        price: Option<f64>,
+    ) -> Result<models::Order, String> {
+        self.close_position_in(base::Market::Futures, symbol, price).await
+    }
+
+    pub async fn get_active_futures_orders(
+        &self,
+        symbol: Option<coin::Symbol>,
+    ) -> Result<models::Orders, String> {
+        self.get_active_orders_in(base::Market::Futures, symbol).await
+    }
+
+    /// Use `with_reduce_only` on the order to make sure it only decreases the position.
+    pub async fn create_futures_market_order(
+        &self,
+        order: models::typed::CreateMarketOrder,
+    ) -> Result<models::Order, String> {
+        self.create_market_order_in(base::Market::Futures, order).await
+    }
+
+    /// Use `with_reduce_only` on the order to make sure it only decreases the position.
+    pub async fn create_futures_limit_order(
+        &self,
+        order: models::typed::CreateLimitOrder,
+    ) -> Result<models::Order, String> {
+        self.create_limit_order_in(base::Market::Futures, order).await
+    }
+
+    pub async fn cancel_all_futures_orders(
+        &self,
+        symbol: Option<coin::Symbol>,
+    ) -> Result<models::Orders, String> {
+        self.cancel_all_orders_in(base::Market::Futures, symbol).await
+    }
+
+    pub async fn cancel_futures_order_by_id(
+        &self,
+        id: &str,
+    ) -> Result<models::Order, String> {
+        self.cancel_order_by_id_in(base::Market::Futures, id).await
+    }
+
+    /// One page of the history. `limit` and `offset` count transactions before
+    /// the currency, type and status filters of `filter` are applied.
+    pub async fn get_transactions(
+        &self,
+        filter: &transaction::TransactionFilter,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<models::Transactions, String> {
+        let page = self.get_transactions_page(filter, limit, offset).await?;
+        Ok(page.into_iter()
+            .filter(|transaction| filter.matches(transaction))
+            .collect())
+    }
+
+    /// Walks the whole history matching `filter` page by page.
+    pub fn get_all_transactions(
+        &self,
+        filter: transaction::TransactionFilter,
+    ) -> impl futures::Stream<Item = Result<models::Transaction, String>> + '_ {
+        let limit = transaction::TransactionFilter::MAX_LIMIT;
+        futures::stream::try_unfold(Some(0), move |offset| {
+            let filter = filter.clone();
+            async move {
+                let offset = match offset {
+                    Some(offset) => offset,
+                    None => return Ok(None),
+                };
+                let page = self.get_transactions_page(&filter, Some(limit), Some(offset)).await?;
+                let next_offset = if (page.len() as u64) < limit {
+                    None
+                } else {
+                    Some(offset + limit)
+                };
+                let page = page.into_iter()
+                    .filter(|transaction| filter.matches(transaction))
+                    .collect::<Vec<_>>();
+                Ok::<_, String>(Some((page, next_offset)))
+            }
+        })
+        .map_ok(|page| futures::stream::iter(page.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    async fn get_transactions_page(
+        &self,
+        filter: &transaction::TransactionFilter,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<models::Transactions, String> {
+        let mut url = self.auth_context.base_url.clone();
+        url.path_segments_mut()
+            .expect(BAD_URL)
+            .push(Self::ACCOUNT)
+            .push(Self::TRANSACTIONS);
+        filter.append_to(&mut url);
+        if let Some(limit) = limit {
+            url.query_pairs_mut()
+                .append_pair("limit", &format!("{}", limit));
+        }
+        if let Some(offset) = offset {
+            url.query_pairs_mut()
+                .append_pair("offset", &format!("{}", offset));
+        }
+        let (_header, body) = process_with_empty_body(
+            &self.client,
+            &self.auth_context,
+            url,
+            hyper::Method::GET).await?;
+        match extractor::extract_transactions(body).await {
+            Some(transactions) => Ok(transactions),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+}
+
+fn get_message(
+    method: hyper::Method,
+    timestamp: &str,
+    path_with_query: &str,
+    body: &str,
+) -> String {
+    format!("{}{}{}{}", method, timestamp, path_with_query, body)
+}
+
+fn get_message_v3(
+    method: hyper::Method,
+    path_with_query: &str,
+    body: &str,
+    timestamp: &str,
+) -> String {
+    format!("{}{}{}{}", method, path_with_query, body, timestamp)
+}
+
+/// Swaps the API version of a v2 base URL, a v3 one is returned as is.
+fn v3_url(base_url: &url::Url) -> Result<url::Url, String> {
+    let segments = base_url.path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let version = match segments.iter().rposition(|segment| *segment == "2" || *segment == "3") {
+        Some(version) => version,
+        None => return Err(format!("Base URL {} has no API version segment", base_url)),
+    };
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .expect(BAD_URL)
+        .clear()
+        .extend(&segments[..version])
+        .push("3")
+        .extend(&segments[version + 1..]);
+    Ok(url)
+}
+
+fn is_permanent(status: hyper::StatusCode) -> bool {
+    status.is_client_error()
+        && status != hyper::StatusCode::REQUEST_TIMEOUT
+        && status != hyper::StatusCode::TOO_MANY_REQUESTS
+}
+
+async fn process_with_empty_body<TConnector>(
+    client: &hyper::Client<TConnector>,
+    auth_context: &context::AuthContext,
+    url: url::Url,
+    method: hyper::Method,
+) -> Result<(http::response::Parts, hyper::Body), String>
+where
+    TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
+{
+    let body = hyper::Body::empty();
+    let body = hyper::body::to_bytes(body).await.expect(BAD_BODY);
+    let body = String::from_utf8(body.to_vec()).expect("Body must be valid UTF-8");
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let path_with_query = &url[url::Position::BeforePath..];
+    let message = get_message(method.clone(), &timestamp, path_with_query, &body);
+    let jwt = auth_context.sign(message, timestamp);
+    let request = match hyper::Request::builder()
+        .header("Accept", "application/json")
+        .header("Authorization", jwt)
+        .uri(url.to_string())
+        .method(method)
+        .body(hyper::Body::empty()) {
+        Ok(request) => request,
+        Err(error) => return Err(format!("Failed create request: {:#?}", error)),
+    };
+    match client.request(request).await {
+        Ok(response) => Ok(response.into_parts()),
+        Err(error) => Err(format!("Failed to craete request: {:#?}", error)),
     }
 }
 
@@ -304,3 +1521,92 @@ where
         Err(error) => Err(format!("Failed to craete request: {:#?}", error)),
     }
 }
+
+/// Signs with the API v3 scheme, for endpoints only available in v3.
+async fn process_v3<TConnector>(
+    client: &hyper::Client<TConnector>,
+    auth_context: &context::AuthContext,
+    url: url::Url,
+    method: hyper::Method,
+    body_bytes: Vec<u8>,
+) -> Result<(http::response::Parts, hyper::Body), String>
+where
+    TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
+{
+    let body = String::from_utf8(body_bytes)
+        .expect("Body must be valid UTF-8");
+    let timestamp = chrono::Utc::now().timestamp_millis().to_string();
+    let path_with_query = &url[url::Position::BeforePath..];
+    let message = get_message_v3(
+        method.clone(),
+        path_with_query,
+        &body,
+        &timestamp);
+    let jwt = auth_context.sign_v3(message, timestamp);
+    let request = match hyper::Request::builder()
+        .header("Content-Type", "application/json")
+        .header("Authorization", jwt)
+        .uri(url.to_string())
+        .method(method)
+        .body(hyper::Body::from(body)) {
+        Ok(request) => request,
+        Err(error) => return Err(format!("Failed create request: {:#?}", error)),
+    };
+    match client.request(request).await {
+        Ok(response) => Ok(response.into_parts()),
+        Err(error) => Err(format!("Failed to create request: {:#?}", error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> url::Url {
+        url::Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn v3_url_swaps_version() {
+        assert_eq!(
+            v3_url(&url("https://api.hitbtc.com/api/2/")).unwrap(),
+            url("https://api.hitbtc.com/api/3"));
+        assert_eq!(
+            v3_url(&url("https://example.com/2/api/2/v")).unwrap(),
+            url("https://example.com/2/api/3/v"));
+    }
+
+    #[test]
+    fn v3_url_keeps_v3() {
+        assert_eq!(
+            v3_url(&url("https://api.hitbtc.com/api/3")).unwrap(),
+            url("https://api.hitbtc.com/api/3"));
+    }
+
+    #[test]
+    fn v3_url_needs_version() {
+        assert!(v3_url(&url("https://api.hitbtc.com/api/")).is_err());
+    }
+
+    #[test]
+    fn v3_message_ends_with_timestamp() {
+        let message = get_message_v3(
+            hyper::Method::POST,
+            "/api/3/spot/order/list?a=1",
+            "{}",
+            "1700000000000");
+        assert_eq!(message, "POST/api/3/spot/order/list?a=1{}1700000000000");
+    }
+
+    #[test]
+    fn v3_header_puts_timestamp_last() {
+        let auth_context = context::AuthContext::new(
+            "key".to_owned(),
+            "secret".to_owned(),
+            url("https://api.hitbtc.com/api/2/"));
+        let header = auth_context.sign_v3("message".to_owned(), "1700000000000".to_owned());
+        let encoded = header.strip_prefix("HS256 ").unwrap();
+        let decoded = String::from_utf8(base64::decode(encoded).unwrap()).unwrap();
+        assert_eq!(decoded, format!("key:{}:1700000000000", auth_context.hmac("message")));
+    }
+}
@@ -1,41 +1,95 @@
-#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
-#[serde(untagged)]
-pub enum Coin {
-    TON,
-    BTC,
-    USDT,
-    Unknown(String),
-}
+/// Exchange currency id. Common coins are available as constants, any other id
+/// is accepted as is, use `coin_registry::CoinRegistry` to check it is listed.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub struct Coin(std::borrow::Cow<'static, str>);
 
 impl Coin {
-    pub fn to_string(self) -> String {
-        match self {
-            Coin::TON => "TON".into(),
-            Coin::BTC => "BTC".into(),
-            Coin::USDT => "USD".into(),
-            Coin::Unknown(name) => name,
+    pub const BTC: Coin = Coin(std::borrow::Cow::Borrowed("BTC"));
+    pub const ETH: Coin = Coin(std::borrow::Cow::Borrowed("ETH"));
+    pub const TON: Coin = Coin(std::borrow::Cow::Borrowed("TON"));
+    pub const USD: Coin = Coin(std::borrow::Cow::Borrowed("USD"));
+    pub const USDT: Coin = Coin(std::borrow::Cow::Borrowed("USDT"));
+    pub const USDC: Coin = Coin(std::borrow::Cow::Borrowed("USDC"));
+
+    /// Longest ids first, so that `USDT` is matched before `USD`.
+    const KNOWN: [Coin; 6] = [
+        Coin::USDT,
+        Coin::USDC,
+        Coin::BTC,
+        Coin::ETH,
+        Coin::TON,
+        Coin::USD,
+    ];
+
+    pub fn new(id: String) -> Coin {
+        match Coin::KNOWN.iter().find(|coin| coin.id() == id) {
+            Some(coin) => coin.clone(),
+            None => Coin(std::borrow::Cow::Owned(id)),
         }
     }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Coin {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str(self.id())
+    }
 }
 
 impl From<&str> for Coin {
     fn from(coin: &str) -> Coin {
-        match coin {
-            "TON" => Coin::TON,
-            "BTC" => Coin::BTC,
-            "USD" => Coin::USDT,
-            other => Coin::Unknown(other.into()),
+        Coin::new(coin.to_owned())
+    }
+}
+
+impl From<String> for Coin {
+    fn from(coin: String) -> Coin {
+        Coin::new(coin)
+    }
+}
+
+impl std::str::FromStr for Coin {
+    type Err = String;
+
+    fn from_str(coin: &str) -> Result<Coin, String> {
+        if coin.is_empty() {
+            return Err("Coin must not be empty".to_owned());
         }
+        Ok(Coin::from(coin))
+    }
+}
+
+impl serde::Serialize for Coin {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Coin {
+    fn deserialize<D>(deserializer: D) -> Result<Coin, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let coin = String::deserialize(deserializer)?;
+        coin.parse().map_err(serde::de::Error::custom)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Symbol {
     pub left: Coin,
     pub right: Coin,
 }
 
 impl Symbol {
+    const SEPARATORS: [char; 3] = ['/', '-', '_'];
+
     pub fn new(left: Coin, right: Coin) -> Symbol {
         Symbol {
             left,
@@ -49,8 +103,97 @@ impl Symbol {
             right: self.left
         }
     }
+}
+
+/// Exchange symbol id, e.g. `ETHBTC`.
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}{}", self.left, self.right)
+    }
+}
+
+/// Accepts `ETH/BTC`, `ETH-BTC` and `ETH_BTC`. Exchange ids without a separator
+/// are split only when one side is a known coin, use
+/// `symbol_registry::SymbolRegistry::parse` otherwise.
+impl std::str::FromStr for Symbol {
+    type Err = String;
+
+    fn from_str(symbol: &str) -> Result<Symbol, String> {
+        if let Some((left, right)) = symbol.split_once(&Symbol::SEPARATORS[..]) {
+            return Ok(Symbol::new(left.parse()?, right.parse()?));
+        }
+        for coin in Coin::KNOWN.iter() {
+            if let Some(left) = symbol.strip_suffix(coin.id()) {
+                if !left.is_empty() {
+                    return Ok(Symbol::new(Coin::from(left), coin.clone()));
+                }
+            }
+            if let Some(right) = symbol.strip_prefix(coin.id()) {
+                if !right.is_empty() {
+                    return Ok(Symbol::new(coin.clone(), Coin::from(right)));
+                }
+            }
+        }
+        Err(format!("Unable to split symbol {} into base and quote", symbol))
+    }
+}
+
+impl serde::Serialize for Symbol {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Symbol {
+    fn deserialize<D>(deserializer: D) -> Result<Symbol, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let symbol = String::deserialize(deserializer)?;
+        symbol.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(left: &str, right: &str) -> Symbol {
+        Symbol::new(Coin::from(left), Coin::from(right))
+    }
+
+    #[test]
+    fn splits_on_separators() {
+        assert_eq!("ETH/BTC".parse(), Ok(symbol("ETH", "BTC")));
+        assert_eq!("DOGE-USDT".parse(), Ok(symbol("DOGE", "USDT")));
+        assert_eq!("XRP_EUR".parse(), Ok(symbol("XRP", "EUR")));
+    }
+
+    #[test]
+    fn splits_exchange_id_on_known_coin() {
+        assert_eq!("ETHBTC".parse(), Ok(symbol("ETH", "BTC")));
+        assert_eq!("DOGEUSDT".parse(), Ok(symbol("DOGE", "USDT")));
+        assert_eq!("BTCEURS".parse(), Ok(symbol("BTC", "EURS")));
+    }
+
+    #[test]
+    fn prefers_longest_known_coin() {
+        assert_eq!("XRPUSDT".parse(), Ok(symbol("XRP", "USDT")));
+        assert_eq!("XRPUSD".parse(), Ok(symbol("XRP", "USD")));
+    }
+
+    #[test]
+    fn rejects_unsplittable_ids() {
+        assert!("DOGEXRP".parse::<Symbol>().is_err());
+        assert!("BTC".parse::<Symbol>().is_err());
+        assert!("ETH/".parse::<Symbol>().is_err());
+    }
 
-    pub fn to_string(self) -> String {
-        format!("{}{}", self.left.to_string(), self.right.to_string())
+    #[test]
+    fn displays_exchange_id() {
+        assert_eq!(symbol("ETH", "BTC").to_string(), "ETHBTC");
     }
 }
@@ -0,0 +1,93 @@
+use super::coin;
+use super::models;
+use super::public_client;
+
+#[derive(Clone, Debug)]
+pub struct CoinInfo {
+    pub coin: coin::Coin,
+    pub full_name: String,
+    /// Number of decimals allowed in withdrawals.
+    pub precision_payout: u32,
+    /// Number of decimals allowed in transfers between accounts.
+    pub precision_transfer: u32,
+    pub payin_enabled: bool,
+    pub payout_enabled: bool,
+    pub transfer_enabled: bool,
+    pub delisted: bool,
+    /// Deposits must carry the payment id (memo) of the deposit address.
+    pub payment_id_required: bool,
+    pub networks: Vec<models::CurrencyNetwork>,
+}
+
+impl CoinInfo {
+    pub fn new(currency: models::PublicCurrency) -> CoinInfo {
+        CoinInfo {
+            coin: coin::Coin::new(currency.id),
+            full_name: currency.full_name,
+            precision_payout: currency.precision_payout,
+            precision_transfer: currency.precision_transfer,
+            payin_enabled: currency.payin_enabled,
+            payout_enabled: currency.payout_enabled,
+            transfer_enabled: currency.transfer_enabled,
+            delisted: currency.delisted,
+            payment_id_required: currency.payin_payment_id,
+            networks: currency.networks,
+        }
+    }
+
+    /// Network used when none is given, the first one if the exchange marks none as default.
+    pub fn default_network(&self) -> Option<&models::CurrencyNetwork> {
+        self.networks.iter()
+            .find(|network| network.default)
+            .or_else(|| self.networks.first())
+    }
+
+    pub fn network(&self, network: &str) -> Option<&models::CurrencyNetwork> {
+        self.networks.iter().find(|info| info.network == network)
+    }
+}
+
+/// Currency metadata keyed by exchange currency id.
+#[derive(Clone, Debug, Default)]
+pub struct CoinRegistry {
+    coins: std::collections::HashMap<coin::Coin, CoinInfo>,
+}
+
+impl CoinRegistry {
+    pub fn new(currencies: Vec<models::PublicCurrency>) -> CoinRegistry {
+        let coins = currencies.into_iter()
+            .map(CoinInfo::new)
+            .map(|info| (info.coin.clone(), info))
+            .collect();
+        CoinRegistry { coins }
+    }
+
+    pub async fn load<TConnector>(
+        client: &public_client::PublicClient<TConnector>,
+    ) -> Result<CoinRegistry, String>
+    where
+        TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
+    {
+        Ok(CoinRegistry::new(client.get_all_currencies().await?))
+    }
+
+    pub fn get(&self, coin: &coin::Coin) -> Option<&CoinInfo> {
+        self.coins.get(coin)
+    }
+
+    pub fn get_by_id(&self, id: &str) -> Option<&CoinInfo> {
+        self.coins.get(&coin::Coin::from(id))
+    }
+
+    /// Fails for ids the exchange does not list.
+    pub fn parse(&self, id: &str) -> Result<coin::Coin, String> {
+        match self.get_by_id(id) {
+            Some(info) => Ok(info.coin.clone()),
+            None => Err(format!("Unknown coin: {}", id)),
+        }
+    }
+
+    pub fn coins(&self) -> impl Iterator<Item = &CoinInfo> {
+        self.coins.values()
+    }
+}
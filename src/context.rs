@@ -14,23 +14,42 @@ impl AuthContext {
         base_url: url::Url,
     ) -> Self {
         AuthContext {
-            public_key: public_key.into(),
-            private_key: private_key.into(),
+            public_key,
+            private_key,
             base_url,
         }
     }
 
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
     pub fn sign(&self, message: String, timestamp: String) -> String {
+        let result = base64::encode(format!(
+            "{}:{}:{}",
+            self.public_key,
+            timestamp,
+            self.hmac(&message)));
+        format!("HS256 {}", result)
+    }
+
+    /// API v3 header, the timestamp is in milliseconds and follows the signature.
+    pub fn sign_v3(&self, message: String, timestamp: String) -> String {
+        let result = base64::encode(format!(
+            "{}:{}:{}",
+            self.public_key,
+            self.hmac(&message),
+            timestamp));
+        format!("HS256 {}", result)
+    }
+
+    /// Hex encoded HMAC-SHA256 of `message` keyed by the private key.
+    pub fn hmac(&self, message: &str) -> String {
         let mut signature = hmac::Hmac::<sha2::Sha256>::new_from_slice(
             self.private_key.as_bytes())
             .expect("HMAC can take key of any size");
         signature.update(message.as_bytes());
         let signature = signature.finalize();
-        let result = base64::encode(&format!(
-            "{}:{}:{}",
-            self.public_key,
-            timestamp,
-            hex::encode(signature.into_bytes())));
-        format!("HS256 {}", result)
+        hex::encode(signature.into_bytes())
     }
 }
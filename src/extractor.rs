@@ -17,6 +17,10 @@ pub async fn extract_order(body: hyper::Body) -> Option<models::Order> {
     read_body(body).await
 }
 
+pub async fn extract_spot_orders(body: hyper::Body) -> Option<Vec<models::SpotOrder>> {
+    read_body(body).await
+}
+
 pub async fn extract_symbols(body: hyper::Body) -> Option<models::Symbols> {
     read_body(body).await
 }
@@ -39,6 +43,78 @@ pub async fn extract_trading_commission(body: hyper::Body) -> Option<models::Tra
     read_body(body).await
 }
 
+pub async fn extract_deposit_address(body: hyper::Body) -> Option<models::DepositAddress> {
+    read_body(body).await
+}
+
+pub async fn extract_deposit_addresses(body: hyper::Body) -> Option<models::DepositAddresses> {
+    read_body(body).await
+}
+
+pub async fn extract_withdrawal(body: hyper::Body) -> Option<models::Withdrawal> {
+    read_body(body).await
+}
+
+pub async fn extract_withdrawal_fee(body: hyper::Body) -> Option<models::WithdrawalFee> {
+    read_body(body).await
+}
+
+pub async fn extract_result(body: hyper::Body) -> Option<models::OperationResult> {
+    read_body(body).await
+}
+
+pub async fn extract_transactions(body: hyper::Body) -> Option<models::Transactions> {
+    read_body(body).await
+}
+
+pub async fn extract_convert_pairs(body: hyper::Body) -> Option<models::ConvertPairs> {
+    read_body(body).await
+}
+
+pub async fn extract_convert_quote(body: hyper::Body) -> Option<models::ConvertQuote> {
+    read_body(body).await
+}
+
+pub async fn extract_transaction(body: hyper::Body) -> Option<models::Transaction> {
+    read_body(body).await
+}
+
+pub async fn extract_internal_transfer(body: hyper::Body) -> Option<models::InternalTransfer> {
+    read_body(body).await
+}
+
+pub async fn extract_sub_accounts(body: hyper::Body) -> Option<models::SubAccountsResult> {
+    read_body(body).await
+}
+
+pub async fn extract_sub_account_transfer(
+    body: hyper::Body,
+) -> Option<models::SubAccountTransfer> {
+    read_body(body).await
+}
+
+pub async fn extract_sub_account_balance(
+    body: hyper::Body,
+) -> Option<models::SubAccountBalanceResult> {
+    read_body(body).await
+}
+
+pub async fn extract_sub_account_acls(body: hyper::Body) -> Option<models::SubAccountAclsResult> {
+    read_body(body).await
+}
+
+pub async fn extract_isolated_accounts(body: hyper::Body) -> Option<models::IsolatedAccounts> {
+    read_body(body).await
+}
+
+pub async fn extract_isolated_account(body: hyper::Body) -> Option<models::IsolatedAccount> {
+    read_body(body).await
+}
+
+pub async fn extract_positions(body: hyper::Body) -> Option<models::Positions> {
+    read_body(body).await
+}
+
 async fn read_body<TResult>(body: hyper::Body) -> Option<TResult>
 where
     TResult: serde::de::DeserializeOwned,
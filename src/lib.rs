@@ -9,3 +9,15 @@ pub mod public_client;
 pub mod order;
 pub mod base;
 pub mod error;
+pub mod transaction;
+pub mod accounts;
+pub mod websocket;
+pub mod market_stream;
+pub mod trading_stream;
+pub mod wallet_stream;
+pub mod local_orderbook;
+pub mod analytics;
+pub mod symbol_registry;
+pub mod coin_registry;
+pub mod pre_trade;
+pub mod risk_guard;
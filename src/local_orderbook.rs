@@ -0,0 +1,292 @@
+use std::str::FromStr;
+use super::base;
+use super::market_stream;
+use super::models;
+
+pub type Level = (rust_decimal::Decimal, rust_decimal::Decimal);
+
+/// Copy of the book at one sequence number, best levels first.
+#[derive(Clone, Debug)]
+pub struct OrderBookSnapshot {
+    pub symbol: String,
+    pub sequence: Option<u64>,
+    pub asks: Vec<Level>,
+    pub bids: Vec<Level>,
+}
+
+/// Order book kept up to date from a REST or WebSocket snapshot plus
+/// incremental WebSocket updates. Levels map price to size.
+#[derive(Clone, Debug)]
+pub struct LocalOrderBook {
+    symbol: String,
+    /// Set by a snapshot, updates are rejected before.
+    seeded: bool,
+    sequence: Option<u64>,
+    asks: std::collections::BTreeMap<rust_decimal::Decimal, rust_decimal::Decimal>,
+    bids: std::collections::BTreeMap<rust_decimal::Decimal, rust_decimal::Decimal>,
+}
+
+impl LocalOrderBook {
+    /// Empty book, a snapshot must be applied before any update.
+    pub fn new(symbol: String) -> LocalOrderBook {
+        LocalOrderBook {
+            symbol,
+            seeded: false,
+            sequence: None,
+            asks: std::collections::BTreeMap::new(),
+            bids: std::collections::BTreeMap::new(),
+        }
+    }
+
+    /// Seeds the book from `PublicClient::get_symbol_from_orderbook`.
+    /// REST snapshots carry no sequence number, so the next update is accepted as is.
+    pub fn from_rest(
+        symbol: String,
+        orderbook: &models::OrderbookExactSymbol,
+    ) -> Result<LocalOrderBook, String> {
+        let mut book = LocalOrderBook::new(symbol);
+        book.seeded = true;
+        for price in orderbook.ask.iter() {
+            let (price, size) = parse_level(&price.price, &price.size)?;
+            set_level(&mut book.asks, price, size);
+        }
+        for price in orderbook.bid.iter() {
+            let (price, size) = parse_level(&price.price, &price.size)?;
+            set_level(&mut book.bids, price, size);
+        }
+        Ok(book)
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    pub fn is_seeded(&self) -> bool {
+        self.seeded
+    }
+
+    /// Applies a WebSocket snapshot or update. Fails on an update before any
+    /// snapshot or on a sequence gap, in which case the book is left untouched
+    /// and must be reseeded from a snapshot.
+    pub fn apply(&mut self, event: &market_stream::OrderBookEvent) -> Result<(), String> {
+        if event.symbol != self.symbol {
+            return Err(format!(
+                "Order book of {} got an update of {}",
+                self.symbol,
+                event.symbol));
+        }
+        match event.kind {
+            market_stream::NotificationKind::Update => {
+                if !self.seeded {
+                    return Err(format!(
+                        "Order book of {} got an update before a snapshot",
+                        self.symbol));
+                }
+                if let Some(sequence) = self.sequence {
+                    if event.sequence <= sequence {
+                        return Ok(());
+                    }
+                    if event.sequence != sequence + 1 {
+                        return Err(format!(
+                            "Sequence gap in {}: expected {}, received {}",
+                            self.symbol,
+                            sequence + 1,
+                            event.sequence));
+                    }
+                }
+            },
+            market_stream::NotificationKind::Snapshot
+            | market_stream::NotificationKind::Data => (),
+        }
+        let asks = to_levels(&event.raw_asks)?;
+        let bids = to_levels(&event.raw_bids)?;
+        if event.kind != market_stream::NotificationKind::Update {
+            self.asks.clear();
+            self.bids.clear();
+        }
+        for (price, size) in asks {
+            set_level(&mut self.asks, price, size);
+        }
+        for (price, size) in bids {
+            set_level(&mut self.bids, price, size);
+        }
+        self.seeded = true;
+        self.sequence = Some(event.sequence);
+        Ok(())
+    }
+
+    pub fn best_ask(&self) -> Option<Level> {
+        self.asks.iter().next().map(|(price, size)| (*price, *size))
+    }
+
+    pub fn best_bid(&self) -> Option<Level> {
+        self.bids.iter().next_back().map(|(price, size)| (*price, *size))
+    }
+
+    pub fn spread(&self) -> Option<rust_decimal::Decimal> {
+        match (self.best_ask(), self.best_bid()) {
+            (Some((ask, _)), Some((bid, _))) => Some(ask - bid),
+            _ => None,
+        }
+    }
+
+    pub fn mid_price(&self) -> Option<rust_decimal::Decimal> {
+        match (self.best_ask(), self.best_bid()) {
+            (Some((ask, _)), Some((bid, _))) => Some((ask + bid) / rust_decimal::Decimal::TWO),
+            _ => None,
+        }
+    }
+
+    /// Best `levels` levels of `side`: asks for `Sell`, bids for `Buy`.
+    pub fn depth(&self, side: base::Side, levels: usize) -> Vec<Level> {
+        match side {
+            base::Side::Sell => self.asks.iter()
+                .take(levels)
+                .map(|(price, size)| (*price, *size))
+                .collect(),
+            base::Side::Buy => self.bids.iter()
+                .rev()
+                .take(levels)
+                .map(|(price, size)| (*price, *size))
+                .collect(),
+        }
+    }
+
+    pub fn snapshot(&self) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            symbol: self.symbol.clone(),
+            sequence: self.sequence,
+            asks: self.depth(base::Side::Sell, self.asks.len()),
+            bids: self.depth(base::Side::Buy, self.bids.len()),
+        }
+    }
+}
+
+fn set_level(
+    side: &mut std::collections::BTreeMap<rust_decimal::Decimal, rust_decimal::Decimal>,
+    price: rust_decimal::Decimal,
+    size: rust_decimal::Decimal,
+) {
+    if size.is_zero() {
+        side.remove(&price);
+    } else {
+        side.insert(price, size);
+    }
+}
+
+fn to_levels(levels: &[(String, String)]) -> Result<Vec<Level>, String> {
+    levels.iter()
+        .map(|(price, size)| parse_level(price, size))
+        .collect()
+}
+
+fn parse_level(price: &str, size: &str) -> Result<Level, String> {
+    let price = rust_decimal::Decimal::from_str(price)
+        .map_err(|error| format!("Bad price {}: {:#?}", price, error))?;
+    let size = rust_decimal::Decimal::from_str(size)
+        .map_err(|error| format!("Bad size {}: {:#?}", size, error))?;
+    Ok((price, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(
+        kind: market_stream::NotificationKind,
+        sequence: u64,
+        asks: &[(&str, &str)],
+        bids: &[(&str, &str)],
+    ) -> market_stream::OrderBookEvent {
+        let raw = |levels: &[(&str, &str)]| levels.iter()
+            .map(|(price, size)| (price.to_string(), size.to_string()))
+            .collect::<Vec<_>>();
+        market_stream::OrderBookEvent {
+            symbol: "BTCUSDT".to_owned(),
+            kind,
+            timestamp: 0,
+            sequence,
+            asks: Vec::new(),
+            bids: Vec::new(),
+            raw_asks: raw(asks),
+            raw_bids: raw(bids),
+        }
+    }
+
+    fn decimal(value: &str) -> rust_decimal::Decimal {
+        rust_decimal::Decimal::from_str(value).unwrap()
+    }
+
+    fn seeded() -> LocalOrderBook {
+        let mut book = LocalOrderBook::new("BTCUSDT".to_owned());
+        book.apply(&event(
+            market_stream::NotificationKind::Snapshot,
+            10,
+            &[("101", "1"), ("102", "2")],
+            &[("99", "1"), ("98", "2")])).unwrap();
+        book
+    }
+
+    #[test]
+    fn rejects_update_before_snapshot() {
+        let mut book = LocalOrderBook::new("BTCUSDT".to_owned());
+        let update = event(market_stream::NotificationKind::Update, 1, &[("101", "1")], &[]);
+        assert!(book.apply(&update).is_err());
+        assert!(!book.is_seeded());
+        assert_eq!(book.best_ask(), None);
+    }
+
+    #[test]
+    fn applies_contiguous_update() {
+        let mut book = seeded();
+        book.apply(&event(
+            market_stream::NotificationKind::Update,
+            11,
+            &[("101", "0"), ("100.5", "3")],
+            &[])).unwrap();
+        assert_eq!(book.sequence(), Some(11));
+        assert_eq!(book.best_ask(), Some((decimal("100.5"), decimal("3"))));
+        assert_eq!(book.best_bid(), Some((decimal("99"), decimal("1"))));
+    }
+
+    #[test]
+    fn ignores_stale_update() {
+        let mut book = seeded();
+        book.apply(&event(market_stream::NotificationKind::Update, 10, &[("101", "0")], &[])).unwrap();
+        assert_eq!(book.sequence(), Some(10));
+        assert_eq!(book.best_ask(), Some((decimal("101"), decimal("1"))));
+    }
+
+    #[test]
+    fn fails_on_gap_and_leaves_book_untouched() {
+        let mut book = seeded();
+        let gap = event(market_stream::NotificationKind::Update, 12, &[("101", "0")], &[]);
+        assert!(book.apply(&gap).is_err());
+        assert_eq!(book.sequence(), Some(10));
+        assert_eq!(book.best_ask(), Some((decimal("101"), decimal("1"))));
+    }
+
+    #[test]
+    fn snapshot_replaces_levels_after_gap() {
+        let mut book = seeded();
+        book.apply(&event(market_stream::NotificationKind::Snapshot, 20, &[("105", "1")], &[])).unwrap();
+        assert_eq!(book.sequence(), Some(20));
+        assert_eq!(book.depth(base::Side::Sell, 10), vec![(decimal("105"), decimal("1"))]);
+        assert_eq!(book.best_bid(), None);
+    }
+
+    #[test]
+    fn keeps_exchange_precision() {
+        let mut book = LocalOrderBook::new("BTCUSDT".to_owned());
+        book.apply(&event(
+            market_stream::NotificationKind::Snapshot,
+            1,
+            &[("12345678.123456789", "0.000000001")],
+            &[])).unwrap();
+        assert_eq!(book.best_ask(), Some((decimal("12345678.123456789"), decimal("0.000000001"))));
+    }
+}
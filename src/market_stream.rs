@@ -0,0 +1,544 @@
+use std::str::FromStr;
+use super::base;
+use super::coin;
+use super::models;
+use super::websocket;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum NotificationKind {
+    Snapshot,
+    Update,
+    Data,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Depth {
+    D5,
+    D10,
+    D20,
+}
+
+impl Depth {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            Depth::D5 => "D5",
+            Depth::D10 => "D10",
+            Depth::D20 => "D20",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Speed {
+    Ms100,
+    Ms500,
+    Ms1000,
+}
+
+impl Speed {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            Speed::Ms100 => "100ms",
+            Speed::Ms500 => "500ms",
+            Speed::Ms1000 => "1000ms",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TickerSpeed {
+    S1,
+    S3,
+}
+
+impl TickerSpeed {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            TickerSpeed::S1 => "1s",
+            TickerSpeed::S3 => "3s",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Period {
+    M1,
+    M3,
+    M5,
+    M15,
+    M30,
+    H1,
+    H4,
+    D1,
+    D7,
+    Month1,
+}
+
+impl Period {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            Period::M1 => "M1",
+            Period::M3 => "M3",
+            Period::M5 => "M5",
+            Period::M15 => "M15",
+            Period::M30 => "M30",
+            Period::H1 => "H1",
+            Period::H4 => "H4",
+            Period::D1 => "D1",
+            Period::D7 => "D7",
+            Period::Month1 => "1M",
+        }
+    }
+}
+
+pub struct OrderBookEvent {
+    pub symbol: String,
+    pub kind: NotificationKind,
+    pub timestamp: u64,
+    pub sequence: u64,
+    pub asks: Vec<models::typed::Price>,
+    pub bids: Vec<models::typed::Price>,
+    /// Price and size strings as sent, parsed exactly by `LocalOrderBook`.
+    pub(crate) raw_asks: Vec<(String, String)>,
+    pub(crate) raw_bids: Vec<(String, String)>,
+}
+
+pub struct TopOfBookEvent {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub ask: Option<models::typed::Price>,
+    pub bid: Option<models::typed::Price>,
+}
+
+pub struct Trade {
+    pub id: u64,
+    pub timestamp: u64,
+    pub price: f64,
+    pub quantity: f64,
+    pub side: base::Side,
+}
+
+pub struct TradesEvent {
+    pub symbol: String,
+    pub kind: NotificationKind,
+    pub trades: Vec<Trade>,
+}
+
+pub struct TickerEvent {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub ask: Option<f64>,
+    pub bid: Option<f64>,
+    pub last: Option<f64>,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub volume: Option<f64>,
+    pub volume_quote: Option<f64>,
+}
+
+pub struct Candle {
+    pub timestamp: u64,
+    pub open: f64,
+    pub close: f64,
+    pub high: f64,
+    pub low: f64,
+    pub volume: f64,
+    pub volume_quote: f64,
+}
+
+pub struct CandlesEvent {
+    pub symbol: String,
+    pub kind: NotificationKind,
+    pub candles: Vec<Candle>,
+}
+
+/// Events whose sequence numbers must be contiguous between snapshots.
+trait Sequenced {
+    fn sequence(&self) -> Option<(&str, NotificationKind, u64)> {
+        None
+    }
+}
+
+impl Sequenced for OrderBookEvent {
+    fn sequence(&self) -> Option<(&str, NotificationKind, u64)> {
+        Some((&self.symbol, self.kind, self.sequence))
+    }
+}
+
+impl Sequenced for TopOfBookEvent {}
+
+impl Sequenced for TradesEvent {}
+
+impl Sequenced for TickerEvent {}
+
+impl Sequenced for CandlesEvent {}
+
+#[derive(serde::Deserialize)]
+struct RawOrderBook {
+    t: u64,
+    s: u64,
+    #[serde(default)]
+    a: Vec<(String, String)>,
+    #[serde(default)]
+    b: Vec<(String, String)>,
+}
+
+#[derive(serde::Deserialize)]
+struct RawTopOfBook {
+    t: u64,
+    a: Option<String>,
+    #[serde(rename="A")]
+    ask_size: Option<String>,
+    b: Option<String>,
+    #[serde(rename="B")]
+    bid_size: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct RawTrade {
+    t: u64,
+    i: u64,
+    p: String,
+    q: String,
+    s: String,
+}
+
+#[derive(serde::Deserialize)]
+struct RawTicker {
+    t: u64,
+    a: Option<String>,
+    b: Option<String>,
+    c: Option<String>,
+    o: Option<String>,
+    h: Option<String>,
+    l: Option<String>,
+    v: Option<String>,
+    q: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct RawCandle {
+    t: u64,
+    o: String,
+    c: String,
+    h: String,
+    l: String,
+    v: String,
+    q: String,
+}
+
+/// Client of the public market data WebSocket.
+pub struct MarketDataClient {
+    connection: websocket::Connection,
+}
+
+impl MarketDataClient {
+    pub async fn connect(url: url::Url) -> Result<MarketDataClient, String> {
+        let connection = websocket::Connection::connect(url).await?;
+        Ok(MarketDataClient { connection })
+    }
+
+    pub async fn connect_with(
+        url: url::Url,
+        policy: websocket::ReconnectPolicy,
+    ) -> Result<MarketDataClient, String> {
+        let connection = websocket::Connection::connect_with(url, policy).await?;
+        Ok(MarketDataClient { connection })
+    }
+
+    pub async fn subscribe_full_orderbook(
+        &self,
+        symbols: Vec<coin::Symbol>,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<OrderBookEvent>>, String> {
+        self.subscribe("orderbook/full", symbols, to_orderbook).await
+    }
+
+    pub async fn subscribe_partial_orderbook(
+        &self,
+        symbols: Vec<coin::Symbol>,
+        depth: Depth,
+        speed: Speed,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<OrderBookEvent>>, String> {
+        let channel = format!("orderbook/{}/{}", depth.to_string(), speed.to_string());
+        self.subscribe(&channel, symbols, to_orderbook).await
+    }
+
+    pub async fn subscribe_top_of_book(
+        &self,
+        symbols: Vec<coin::Symbol>,
+        speed: Speed,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<TopOfBookEvent>>, String> {
+        let channel = format!("orderbook/top/{}", speed.to_string());
+        self.subscribe(&channel, symbols, to_top_of_book).await
+    }
+
+    pub async fn subscribe_trades(
+        &self,
+        symbols: Vec<coin::Symbol>,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<TradesEvent>>, String> {
+        self.subscribe("trades", symbols, to_trades).await
+    }
+
+    pub async fn subscribe_tickers(
+        &self,
+        symbols: Vec<coin::Symbol>,
+        speed: TickerSpeed,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<TickerEvent>>, String> {
+        let channel = format!("ticker/{}", speed.to_string());
+        self.subscribe(&channel, symbols, to_ticker).await
+    }
+
+    pub async fn subscribe_candles(
+        &self,
+        symbols: Vec<coin::Symbol>,
+        period: Period,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<CandlesEvent>>, String> {
+        let channel = format!("candles/{}", period.to_string());
+        self.subscribe(&channel, symbols, to_candles).await
+    }
+
+    async fn subscribe<TEvent>(
+        &self,
+        channel: &str,
+        symbols: Vec<coin::Symbol>,
+        parse: fn(String, NotificationKind, serde_json::Value) -> Result<TEvent, String>,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<TEvent>>, String>
+    where
+        TEvent: Sequenced,
+    {
+        let symbols: Vec<String> = symbols.into_iter()
+            .map(|symbol| symbol.to_string())
+            .collect();
+        let notifications = self.connection.listen(channel)?;
+        let mut message = serde_json::Map::new();
+        message.insert("method".to_owned(), "subscribe".into());
+        message.insert("ch".to_owned(), channel.into());
+        message.insert("params".to_owned(), serde_json::json!({ "symbols": symbols }));
+        self.connection.subscribe(message, &[channel]).await?;
+        Ok(events(notifications, symbols, parse))
+    }
+}
+
+fn events<TEvent>(
+    notifications: impl futures::Stream<Item = websocket::Notification>,
+    symbols: Vec<String>,
+    parse: fn(String, NotificationKind, serde_json::Value) -> Result<TEvent, String>,
+) -> impl futures::Stream<Item = websocket::StreamEvent<TEvent>>
+where
+    TEvent: Sequenced,
+{
+    use futures::StreamExt;
+    let mut sequences = std::collections::HashMap::<String, u64>::new();
+    notifications.flat_map(move |notification| {
+        let notification = match notification {
+            websocket::Notification::Message(notification) => notification,
+            websocket::Notification::Reconnected => {
+                sequences.clear();
+                return futures::stream::iter(vec![websocket::StreamEvent::Reconnected]);
+            },
+            websocket::Notification::LoginFailed(error) => {
+                return futures::stream::iter(vec![websocket::StreamEvent::LoginFailed(error)]);
+            },
+            websocket::Notification::RestoreFailed(error) => {
+                return futures::stream::iter(vec![websocket::StreamEvent::RestoreFailed(error)]);
+            },
+        };
+        let mut events = Vec::new();
+        for (symbol, kind, data) in split(notification) {
+            if !symbols.contains(&symbol) {
+                continue;
+            }
+            let event = match parse(symbol, kind, data) {
+                Ok(event) => event,
+                Err(error) => {
+                    log::error!("Error on reading market data: {}", error);
+                    continue;
+                },
+            };
+            if let Some((symbol, kind, sequence)) = event.sequence() {
+                let previous = sequences.insert(symbol.to_owned(), sequence);
+                if let (NotificationKind::Update, Some(previous)) = (kind, previous) {
+                    if sequence != previous + 1 {
+                        events.push(websocket::StreamEvent::GapDetected {
+                            symbol: symbol.to_owned(),
+                            expected: previous + 1,
+                            received: sequence,
+                        });
+                    }
+                }
+            }
+            events.push(websocket::StreamEvent::Event(event));
+        }
+        futures::stream::iter(events)
+    })
+}
+
+fn split(
+    notification: serde_json::Value,
+) -> Vec<(String, NotificationKind, serde_json::Value)> {
+    let (kind, payload) = match notification {
+        serde_json::Value::Object(mut message) => {
+            if let Some(payload) = message.remove("snapshot") {
+                (NotificationKind::Snapshot, payload)
+            } else if let Some(payload) = message.remove("update") {
+                (NotificationKind::Update, payload)
+            } else if let Some(payload) = message.remove("data") {
+                (NotificationKind::Data, payload)
+            } else {
+                return Vec::new();
+            }
+        },
+        _ => return Vec::new(),
+    };
+    match payload {
+        serde_json::Value::Object(payload) => payload.into_iter()
+            .map(|(symbol, data)| (symbol, kind, data))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn to_orderbook(
+    symbol: String,
+    kind: NotificationKind,
+    data: serde_json::Value,
+) -> Result<OrderBookEvent, String> {
+    let raw: RawOrderBook = from_value(data)?;
+    Ok(OrderBookEvent {
+        symbol,
+        kind,
+        timestamp: raw.t,
+        sequence: raw.s,
+        asks: to_prices(&raw.a)?,
+        bids: to_prices(&raw.b)?,
+        raw_asks: raw.a,
+        raw_bids: raw.b,
+    })
+}
+
+fn to_top_of_book(
+    symbol: String,
+    _kind: NotificationKind,
+    data: serde_json::Value,
+) -> Result<TopOfBookEvent, String> {
+    let raw: RawTopOfBook = from_value(data)?;
+    Ok(TopOfBookEvent {
+        symbol,
+        timestamp: raw.t,
+        ask: to_price(raw.a, raw.ask_size)?,
+        bid: to_price(raw.b, raw.bid_size)?,
+    })
+}
+
+fn to_trades(
+    symbol: String,
+    kind: NotificationKind,
+    data: serde_json::Value,
+) -> Result<TradesEvent, String> {
+    let raw: Vec<RawTrade> = from_value(data)?;
+    let trades = raw.into_iter()
+        .map(|trade| Ok(Trade {
+            id: trade.i,
+            timestamp: trade.t,
+            price: parse_f64(&trade.p)?,
+            quantity: parse_f64(&trade.q)?,
+            side: match trade.s.as_str() {
+                "buy" => base::Side::Buy,
+                "sell" => base::Side::Sell,
+                other => return Err(format!("Unknown trade side: {}", other)),
+            },
+        }))
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(TradesEvent {
+        symbol,
+        kind,
+        trades,
+    })
+}
+
+fn to_ticker(
+    symbol: String,
+    _kind: NotificationKind,
+    data: serde_json::Value,
+) -> Result<TickerEvent, String> {
+    let raw: RawTicker = from_value(data)?;
+    Ok(TickerEvent {
+        symbol,
+        timestamp: raw.t,
+        ask: parse_optional(raw.a)?,
+        bid: parse_optional(raw.b)?,
+        last: parse_optional(raw.c)?,
+        open: parse_optional(raw.o)?,
+        high: parse_optional(raw.h)?,
+        low: parse_optional(raw.l)?,
+        volume: parse_optional(raw.v)?,
+        volume_quote: parse_optional(raw.q)?,
+    })
+}
+
+fn to_candles(
+    symbol: String,
+    kind: NotificationKind,
+    data: serde_json::Value,
+) -> Result<CandlesEvent, String> {
+    let raw: Vec<RawCandle> = from_value(data)?;
+    let candles = raw.into_iter()
+        .map(|candle| Ok(Candle {
+            timestamp: candle.t,
+            open: parse_f64(&candle.o)?,
+            close: parse_f64(&candle.c)?,
+            high: parse_f64(&candle.h)?,
+            low: parse_f64(&candle.l)?,
+            volume: parse_f64(&candle.v)?,
+            volume_quote: parse_f64(&candle.q)?,
+        }))
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(CandlesEvent {
+        symbol,
+        kind,
+        candles,
+    })
+}
+
+fn from_value<TResult>(data: serde_json::Value) -> Result<TResult, String>
+where
+    TResult: serde::de::DeserializeOwned,
+{
+    serde_json::from_value(data)
+        .map_err(|error| format!("Unexpected payload: {:#?}", error))
+}
+
+fn to_prices(levels: &[(String, String)]) -> Result<Vec<models::typed::Price>, String> {
+    levels.iter()
+        .map(|(price, size)| Ok(models::typed::Price {
+            rate: parse_f64(price)?,
+            amount: parse_f64(size)?,
+        }))
+        .collect()
+}
+
+fn to_price(
+    price: Option<String>,
+    size: Option<String>,
+) -> Result<Option<models::typed::Price>, String> {
+    match (price, size) {
+        (Some(price), Some(size)) if !price.is_empty() => Ok(Some(models::typed::Price {
+            rate: parse_f64(&price)?,
+            amount: parse_f64(&size)?,
+        })),
+        _ => Ok(None),
+    }
+}
+
+fn parse_optional(value: Option<String>) -> Result<Option<f64>, String> {
+    match value {
+        Some(value) if !value.is_empty() => parse_f64(&value).map(Some),
+        _ => Ok(None),
+    }
+}
+
+fn parse_f64(value: &str) -> Result<f64, String> {
+    f64::from_str(value)
+        .map_err(|error| format!("Bad number {}: {:#?}", value, error))
+}
@@ -24,7 +24,31 @@ pub struct PublicCurrency {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub payout_minimal_amount: Option<String>,
     pub precision_payout: u32,
-    pub precision_transfer: u32
+    pub precision_transfer: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub networks: Vec<CurrencyNetwork>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyNetwork {
+    pub network: String,
+    #[serde(default)]
+    pub protocol: String,
+    #[serde(default)]
+    pub default: bool,
+    pub payin_enabled: bool,
+    pub payout_enabled: bool,
+    #[serde(default)]
+    pub payout_is_payment_id: bool,
+    #[serde(default)]
+    pub payin_payment_id: bool,
+    #[serde(default)]
+    pub payin_confirmations: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payout_fee: Option<String>,
+    #[serde(default)]
+    pub precision_payout: u32,
 }
 
 pub type Orders = Vec<Order>;
@@ -55,6 +79,29 @@ pub struct Order {
     pub expire_time: Option<String>,
 }
 
+/// Order of API v3, returned by `BTCClient::create_order_list`.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SpotOrder {
+    pub id: u64,
+    pub client_order_id: String,
+    pub symbol: String,
+    pub side: String,
+    pub status: String,
+    #[serde(rename="type")]
+    pub order_type: String,
+    pub time_in_force: String,
+    pub quantity: String,
+    pub quantity_cumulative: String,
+    pub price: Option<String>,
+    pub stop_price: Option<String>,
+    #[serde(default)]
+    pub post_only: bool,
+    pub order_list_id: Option<String>,
+    pub contingency_type: Option<String>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+}
+
 #[derive(serde::Serialize, Clone, Debug)]
 pub struct CreateOrder {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -90,6 +137,9 @@ pub struct CreateMarketOrder {
     pub quantity: String,
     #[serde(rename="type")]
     pub order_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename="reduceOnly")]
+    pub reduce_only: Option<bool>,
 }
 
 #[derive(serde::Serialize, Clone, Debug)]
@@ -98,6 +148,35 @@ pub struct CreateLimitOrder {
     pub side: String,
     pub quantity: String,
     pub price: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename="reduceOnly")]
+    pub reduce_only: Option<bool>,
+}
+
+/// Request of API v3, fields are snake case.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CreateOrderList {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_list_id: Option<String>,
+    pub contingency_type: String,
+    pub orders: Vec<CreateListedOrder>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CreateListedOrder {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+    pub symbol: String,
+    pub side: String,
+    #[serde(rename="type")]
+    pub order_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_in_force: Option<String>,
+    pub quantity: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_price: Option<String>,
 }
 
 pub type Symbols = Vec<Symbol>;
@@ -144,10 +223,11 @@ pub struct OrderbookExactSymbol {
     pub ask: Prices,
     pub bid: Prices,
     pub timestamp: String,
+    /// Returned only when the book is requested with `volume`.
     #[serde(rename="askAveragePrice")]
-    pub ask_average_price: String,
+    pub ask_average_price: Option<String>,
     #[serde(rename="bidAveragePrice")]
-    pub bid_average_price: String,
+    pub bid_average_price: Option<String>,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -158,7 +238,272 @@ pub struct TradingCommission {
     pub provide_liquidity_rate: String,
 }
 
+pub type DepositAddresses = Vec<DepositAddress>;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct DepositAddress {
+    pub address: String,
+    #[serde(rename="paymentId")]
+    pub payment_id: Option<String>,
+    #[serde(rename="publicKey")]
+    pub public_key: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CreateWithdrawal {
+    pub currency: String,
+    pub amount: String,
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename="paymentId")]
+    pub payment_id: Option<String>,
+    #[serde(rename="includeFee")]
+    pub include_fee: bool,
+    #[serde(rename="autoCommit")]
+    pub auto_commit: bool,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Withdrawal {
+    pub id: String,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct WithdrawalFee {
+    pub fee: String,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct OperationResult {
+    pub result: bool,
+}
+
+pub type Transactions = Vec<Transaction>;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Transaction {
+    pub id: String,
+    pub index: Option<u64>,
+    pub currency: String,
+    pub amount: String,
+    pub fee: Option<String>,
+    #[serde(rename="networkFee")]
+    pub network_fee: Option<String>,
+    pub address: Option<String>,
+    #[serde(rename="paymentId")]
+    pub payment_id: Option<String>,
+    pub hash: Option<String>,
+    pub status: String,
+    #[serde(rename="type")]
+    pub transaction_type: String,
+    #[serde(rename="createdAt")]
+    pub created_at: String,
+    #[serde(rename="updatedAt")]
+    pub updated_at: Option<String>,
+}
+
+impl Transaction {
+    pub fn status(&self) -> Result<super::transaction::TransactionStatus, String> {
+        self.status.parse()
+    }
+
+    pub fn transaction_type(&self) -> Result<super::transaction::TransactionType, String> {
+        self.transaction_type.parse()
+    }
+}
+
+pub type ConvertPairs = Vec<ConvertPair>;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ConvertPair {
+    #[serde(rename="fromCurrency")]
+    pub from_currency: String,
+    #[serde(rename="toCurrency")]
+    pub to_currency: String,
+    #[serde(rename="minAmount")]
+    pub min_amount: Option<String>,
+    #[serde(rename="maxAmount")]
+    pub max_amount: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CreateConvertQuote {
+    #[serde(rename="fromCurrency")]
+    pub from_currency: String,
+    #[serde(rename="toCurrency")]
+    pub to_currency: String,
+    pub amount: String,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ConvertQuote {
+    pub id: String,
+    #[serde(rename="fromCurrency")]
+    pub from_currency: String,
+    #[serde(rename="toCurrency")]
+    pub to_currency: String,
+    #[serde(rename="fromAmount")]
+    pub from_amount: String,
+    #[serde(rename="toAmount")]
+    pub to_amount: String,
+    pub rate: String,
+    #[serde(rename="expireTime")]
+    pub expire_time: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ExecuteConvert {
+    #[serde(rename="quoteId")]
+    pub quote_id: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CreateInternalTransfer {
+    pub currency: String,
+    pub amount: String,
+    pub by: String,
+    pub identifier: String,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct InternalTransfer {
+    #[serde(rename="result")]
+    pub id: String,
+}
+
+pub type SubAccounts = Vec<SubAccount>;
+
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SubAccountStatus {
+    New,
+    Active,
+    Disable,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SubAccount {
+    pub id: u64,
+    pub email: String,
+    pub status: SubAccountStatus,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SubAccountsResult {
+    pub result: SubAccounts,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct SubAccountIds {
+    pub ids: Vec<u64>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CreateSubAccountTransfer {
+    #[serde(rename="subAccountId")]
+    pub sub_account_id: u64,
+    pub amount: String,
+    pub currency: String,
+    #[serde(rename="type")]
+    pub transfer_type: String,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SubAccountTransfer {
+    #[serde(rename="result")]
+    pub id: String,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SubAccountBalance {
+    pub main: Balance,
+    pub trading: Balance,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SubAccountBalanceResult {
+    pub result: SubAccountBalance,
+}
+
+pub type SubAccountAcls = Vec<SubAccountAcl>;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SubAccountAcl {
+    #[serde(rename="subAccountId")]
+    pub sub_account_id: u64,
+    #[serde(rename="isPayoutEnabled")]
+    pub is_payout_enabled: bool,
+    pub description: Option<String>,
+    #[serde(rename="createdAt")]
+    pub created_at: Option<String>,
+    #[serde(rename="updatedAt")]
+    pub updated_at: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SubAccountAclsResult {
+    pub result: SubAccountAcls,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct UpdateSubAccountAcl {
+    #[serde(rename="isPayoutEnabled")]
+    pub is_payout_enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+pub type IsolatedAccounts = Vec<IsolatedAccount>;
+
+/// Isolated account of a margin or futures symbol.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct IsolatedAccount {
+    pub symbol: String,
+    pub leverage: String,
+    #[serde(rename="marginBalance")]
+    pub margin_balance: String,
+    #[serde(rename="marginBalanceOrders")]
+    pub margin_balance_orders: Option<String>,
+    #[serde(rename="marginAvailable")]
+    pub margin_available: Option<String>,
+    pub position: Option<Position>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct UpdateMarginAccount {
+    #[serde(rename="marginBalance")]
+    pub margin_balance: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leverage: Option<String>,
+    #[serde(rename="strictValidate")]
+    pub strict_validate: bool,
+}
+
+pub type Positions = Vec<Position>;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Position {
+    pub id: Option<u64>,
+    pub symbol: String,
+    pub quantity: String,
+    #[serde(rename="marginBalance")]
+    pub margin_balance: Option<String>,
+    #[serde(rename="entryPrice")]
+    pub entry_price: Option<String>,
+    #[serde(rename="markPrice")]
+    pub mark_price: Option<String>,
+    pub pnl: Option<String>,
+    pub interest: Option<String>,
+    #[serde(rename="liquidationPrice")]
+    pub liquidation_price: Option<String>,
+    #[serde(rename="createdAt")]
+    pub created_at: Option<String>,
+    #[serde(rename="updatedAt")]
+    pub updated_at: Option<String>,
+}
+
 pub mod typed {
+    use std::convert::TryFrom;
     use std::str::FromStr;
     use super::super::base;
     use super::super::coin;
@@ -182,10 +527,39 @@ pub mod typed {
     pub struct Order {
     }
 
+    pub struct DepositAddress {
+        pub currency: String,
+        pub address: String,
+        pub payment_id: Option<String>,
+    }
+
+    impl DepositAddress {
+        /// Fails if the currency requires a payment id (memo) and the address has none,
+        /// as a deposit sent without it can not be credited.
+        pub fn new(
+            address: super::DepositAddress,
+            currency: &super::PublicCurrency,
+        ) -> Result<DepositAddress, String> {
+            let payment_id = address.payment_id
+                .filter(|payment_id| !payment_id.is_empty());
+            if currency.payin_payment_id && payment_id.is_none() {
+                return Err(format!(
+                    "Deposit address for {} requires a payment id",
+                    currency.id));
+            }
+            Ok(DepositAddress {
+                currency: currency.id.clone(),
+                address: address.address,
+                payment_id,
+            })
+        }
+    }
+
     pub struct CreateMarketOrder {
         symbol: coin::Symbol,
         side: base::Side,
         quantity: f64,
+        reduce_only: Option<bool>,
     }
 
     impl CreateMarketOrder {
@@ -198,9 +572,33 @@ pub mod typed {
                 symbol,
                 side,
                 quantity,
+                reduce_only: None,
             }
         }
 
+        /// Only for margin and futures orders.
+        pub fn with_reduce_only(mut self, reduce_only: bool) -> CreateMarketOrder {
+            self.reduce_only = Some(reduce_only);
+            self
+        }
+
+        pub fn with_quantity(mut self, quantity: f64) -> CreateMarketOrder {
+            self.quantity = quantity;
+            self
+        }
+
+        pub fn symbol(&self) -> &coin::Symbol {
+            &self.symbol
+        }
+
+        pub fn side(&self) -> base::Side {
+            self.side
+        }
+
+        pub fn quantity(&self) -> f64 {
+            self.quantity
+        }
+
         pub fn to_model(self) -> super::CreateMarketOrder {
             let symbol = self.symbol.to_string();
             let side = self.side.to_string().to_owned();
@@ -211,6 +609,7 @@ pub mod typed {
                 side,
                 quantity,
                 order_type,
+                reduce_only: self.reduce_only,
             }
         }
     }
@@ -220,6 +619,7 @@ pub mod typed {
         side: base::Side,
         quantity: f64,
         price: f64,
+        reduce_only: Option<bool>,
     }
 
     impl CreateLimitOrder {
@@ -234,15 +634,593 @@ pub mod typed {
                 side,
                 quantity,
                 price,
+                reduce_only: None,
             }
         }
 
+        /// Only for margin and futures orders.
+        pub fn with_reduce_only(mut self, reduce_only: bool) -> CreateLimitOrder {
+            self.reduce_only = Some(reduce_only);
+            self
+        }
+
+        pub fn with_quantity(mut self, quantity: f64) -> CreateLimitOrder {
+            self.quantity = quantity;
+            self
+        }
+
+        pub fn with_price(mut self, price: f64) -> CreateLimitOrder {
+            self.price = price;
+            self
+        }
+
+        pub fn symbol(&self) -> &coin::Symbol {
+            &self.symbol
+        }
+
+        pub fn side(&self) -> base::Side {
+            self.side
+        }
+
+        pub fn quantity(&self) -> f64 {
+            self.quantity
+        }
+
+        pub fn price(&self) -> f64 {
+            self.price
+        }
+
         pub fn to_model(self) -> super::CreateLimitOrder {
             super::CreateLimitOrder {
                 symbol: self.symbol.to_string(),
                 side: self.side.to_string().to_owned(),
                 quantity: format!("{}", self.quantity),
                 price: format!("{}", self.price),
+                reduce_only: self.reduce_only,
+            }
+        }
+    }
+
+    pub struct ListedOrder {
+        symbol: coin::Symbol,
+        side: base::Side,
+        order_type: base::Type,
+        quantity: f64,
+        price: Option<f64>,
+        stop_price: Option<f64>,
+        client_order_id: Option<String>,
+    }
+
+    impl ListedOrder {
+        pub fn market(symbol: coin::Symbol, side: base::Side, quantity: f64) -> ListedOrder {
+            ListedOrder::new(symbol, side, base::Type::Market, quantity, None, None)
+        }
+
+        pub fn limit(
+            symbol: coin::Symbol,
+            side: base::Side,
+            quantity: f64,
+            price: f64,
+        ) -> ListedOrder {
+            ListedOrder::new(symbol, side, base::Type::Limit, quantity, Some(price), None)
+        }
+
+        pub fn stop_market(
+            symbol: coin::Symbol,
+            side: base::Side,
+            quantity: f64,
+            stop_price: f64,
+        ) -> ListedOrder {
+            ListedOrder::new(symbol, side, base::Type::StopMarket, quantity, None, Some(stop_price))
+        }
+
+        pub fn stop_limit(
+            symbol: coin::Symbol,
+            side: base::Side,
+            quantity: f64,
+            price: f64,
+            stop_price: f64,
+        ) -> ListedOrder {
+            ListedOrder::new(
+                symbol,
+                side,
+                base::Type::StopLimit,
+                quantity,
+                Some(price),
+                Some(stop_price))
+        }
+
+        fn new(
+            symbol: coin::Symbol,
+            side: base::Side,
+            order_type: base::Type,
+            quantity: f64,
+            price: Option<f64>,
+            stop_price: Option<f64>,
+        ) -> ListedOrder {
+            ListedOrder {
+                symbol,
+                side,
+                order_type,
+                quantity,
+                price,
+                stop_price,
+                client_order_id: None,
+            }
+        }
+
+        pub fn with_client_order_id(mut self, client_order_id: String) -> ListedOrder {
+            self.client_order_id = Some(client_order_id);
+            self
+        }
+
+        pub fn symbol(&self) -> &coin::Symbol {
+            &self.symbol
+        }
+
+        pub fn order_type(&self) -> base::Type {
+            self.order_type
+        }
+
+        fn into_model(self, time_in_force: Option<&str>) -> super::CreateListedOrder {
+            super::CreateListedOrder {
+                client_order_id: self.client_order_id,
+                symbol: self.symbol.to_string(),
+                side: self.side.to_string().to_owned(),
+                order_type: self.order_type.to_string().to_owned(),
+                time_in_force: time_in_force.map(str::to_owned),
+                quantity: format!("{}", self.quantity),
+                price: self.price.map(|price| format!("{}", price)),
+                stop_price: self.stop_price.map(|stop_price| format!("{}", stop_price)),
+            }
+        }
+    }
+
+    /// Orders placed together, see `base::ContingencyType` for how they interact.
+    pub struct CreateOrderList {
+        contingency_type: base::ContingencyType,
+        orders: Vec<ListedOrder>,
+        order_list_id: Option<String>,
+    }
+
+    impl CreateOrderList {
+        /// Only limit and market orders, all of them are sent as fill-or-kill.
+        pub fn all_or_none(orders: Vec<ListedOrder>) -> Result<CreateOrderList, String> {
+            if orders.len() < 2 {
+                return Err("All-or-none list needs at least two orders".to_owned());
+            }
+            let allowed = [base::Type::Limit, base::Type::Market];
+            if orders.iter().any(|order| !allowed.contains(&order.order_type)) {
+                return Err("All-or-none list accepts only limit and market orders".to_owned());
+            }
+            Ok(CreateOrderList::new(base::ContingencyType::AllOrNone, orders))
+        }
+
+        /// Both orders must share symbol and side.
+        pub fn one_cancel_other(
+            first: ListedOrder,
+            second: ListedOrder,
+        ) -> Result<CreateOrderList, String> {
+            check_cancel_pair(&first, &second)?;
+            Ok(CreateOrderList::new(base::ContingencyType::OneCancelOther, vec![first, second]))
+        }
+
+        pub fn one_trigger_other(
+            trigger: ListedOrder,
+            triggered: ListedOrder,
+        ) -> Result<CreateOrderList, String> {
+            Ok(CreateOrderList::new(
+                base::ContingencyType::OneTriggerOther,
+                vec![trigger, triggered]))
+        }
+
+        /// Bracket: `trigger` opens the position, `first` and `second` are the exits.
+        pub fn one_trigger_one_cancel_other(
+            trigger: ListedOrder,
+            first: ListedOrder,
+            second: ListedOrder,
+        ) -> Result<CreateOrderList, String> {
+            check_cancel_pair(&first, &second)?;
+            Ok(CreateOrderList::new(
+                base::ContingencyType::OneTriggerOneCancelOther,
+                vec![trigger, first, second]))
+        }
+
+        fn new(contingency_type: base::ContingencyType, orders: Vec<ListedOrder>) -> CreateOrderList {
+            CreateOrderList {
+                contingency_type,
+                orders,
+                order_list_id: None,
+            }
+        }
+
+        pub fn with_order_list_id(mut self, order_list_id: String) -> CreateOrderList {
+            self.order_list_id = Some(order_list_id);
+            self
+        }
+
+        pub fn contingency_type(&self) -> base::ContingencyType {
+            self.contingency_type
+        }
+
+        pub fn to_model(self) -> super::CreateOrderList {
+            let time_in_force = match self.contingency_type {
+                base::ContingencyType::AllOrNone => Some("FOK"),
+                _ => None,
+            };
+            super::CreateOrderList {
+                order_list_id: self.order_list_id,
+                contingency_type: self.contingency_type.to_string().to_owned(),
+                orders: self.orders.into_iter()
+                    .map(|order| order.into_model(time_in_force))
+                    .collect(),
+            }
+        }
+    }
+
+    fn check_cancel_pair(first: &ListedOrder, second: &ListedOrder) -> Result<(), String> {
+        if first.symbol != second.symbol {
+            return Err(format!("Orders canceling each other must share symbol {}", first.symbol));
+        }
+        if first.side != second.side {
+            return Err("Orders canceling each other must share side".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Orders created from one `CreateOrderList`.
+    #[derive(Clone, Debug)]
+    pub struct OrderList {
+        pub id: String,
+        pub orders: Vec<super::SpotOrder>,
+    }
+
+    impl OrderList {
+        pub fn new(orders: Vec<super::SpotOrder>) -> Result<OrderList, String> {
+            let id = orders.iter()
+                .find_map(|order| order.order_list_id.clone())
+                .ok_or_else(|| "Created orders carry no order list id".to_owned())?;
+            Ok(OrderList { id, orders })
+        }
+    }
+
+    pub struct CreateWithdrawal {
+        currency: coin::Coin,
+        amount: rust_decimal::Decimal,
+        address: String,
+        payment_id: Option<String>,
+        include_fee: bool,
+        auto_commit: bool,
+        check_address: bool,
+    }
+
+    impl CreateWithdrawal {
+        /// Withdrawal is committed automatically and the fee is charged on top of `amount`.
+        pub fn new(
+            currency: coin::Coin,
+            amount: rust_decimal::Decimal,
+            address: String,
+        ) -> CreateWithdrawal {
+            CreateWithdrawal {
+                currency,
+                amount,
+                address,
+                payment_id: None,
+                include_fee: false,
+                auto_commit: true,
+                check_address: false,
+            }
+        }
+
+        pub fn with_payment_id(mut self, payment_id: String) -> CreateWithdrawal {
+            self.payment_id = Some(payment_id);
+            self
+        }
+
+        pub fn with_include_fee(mut self, include_fee: bool) -> CreateWithdrawal {
+            self.include_fee = include_fee;
+            self
+        }
+
+        /// Without auto commit the withdrawal must be committed or rolled back explicitly.
+        pub fn with_auto_commit(mut self, auto_commit: bool) -> CreateWithdrawal {
+            self.auto_commit = auto_commit;
+            self
+        }
+
+        /// Makes `BTCClient::create_withdrawal` refuse addresses the exchange considers invalid.
+        pub fn with_address_check(mut self, check_address: bool) -> CreateWithdrawal {
+            self.check_address = check_address;
+            self
+        }
+
+        pub fn check_address(&self) -> bool {
+            self.check_address
+        }
+
+        pub fn validate(&self, currency: &super::PublicCurrency) -> Result<(), String> {
+            if !currency.payout_enabled {
+                return Err(format!("Payout is disabled for {}", currency.id));
+            }
+            if self.amount <= rust_decimal::Decimal::ZERO {
+                return Err(format!("Withdrawal amount must be positive: {}", self.amount));
+            }
+            if let Some(minimal_amount) = &currency.payout_minimal_amount {
+                let minimal_amount = match rust_decimal::Decimal::from_str(minimal_amount) {
+                    Ok(minimal_amount) => minimal_amount,
+                    Err(error) => return Err(format!(
+                        "Bad minimal payout amount {}: {:#?}",
+                        minimal_amount,
+                        error)),
+                };
+                if self.amount < minimal_amount {
+                    return Err(format!(
+                        "Withdrawal amount {} is less than minimal payout {} for {}",
+                        self.amount,
+                        minimal_amount,
+                        currency.id));
+                }
+            }
+            if self.amount.normalize().scale() > currency.precision_payout {
+                return Err(format!(
+                    "Withdrawal amount {} exceeds payout precision {} for {}",
+                    self.amount,
+                    currency.precision_payout,
+                    currency.id));
+            }
+            Ok(())
+        }
+
+        pub fn to_model(self) -> super::CreateWithdrawal {
+            super::CreateWithdrawal {
+                currency: self.currency.to_string(),
+                amount: self.amount.normalize().to_string(),
+                address: self.address,
+                payment_id: self.payment_id,
+                include_fee: self.include_fee,
+                auto_commit: self.auto_commit,
+            }
+        }
+    }
+
+    pub struct WithdrawalFee {
+        pub currency: coin::Coin,
+        pub amount: rust_decimal::Decimal,
+        pub fee: rust_decimal::Decimal,
+    }
+
+    impl WithdrawalFee {
+        pub fn new(
+            currency: coin::Coin,
+            amount: rust_decimal::Decimal,
+            fee: super::WithdrawalFee,
+        ) -> Result<WithdrawalFee, String> {
+            match rust_decimal::Decimal::from_str(&fee.fee) {
+                Ok(fee) => Ok(WithdrawalFee {
+                    currency,
+                    amount,
+                    fee,
+                }),
+                Err(error) => Err(format!("Bad fee {}: {:#?}", fee.fee, error)),
+            }
+        }
+
+        pub fn net_amount(&self) -> rust_decimal::Decimal {
+            self.amount - self.fee
+        }
+    }
+
+    /// Keeps balances whose available amount exceeds the payout fee by at least `min_net_amount`.
+    /// Balances without a known fee are skipped.
+    pub fn worth_withdrawing(
+        balance: &super::Balance,
+        fees: &[WithdrawalFee],
+        min_net_amount: rust_decimal::Decimal,
+    ) -> Vec<WithdrawalFee> {
+        balance.iter()
+            .filter_map(|currency| {
+                let coin = coin::Coin::from(currency.currency.as_str());
+                let available = rust_decimal::Decimal::from_str(&currency.available).ok()?;
+                let fee = fees.iter().find(|fee| fee.currency == coin)?;
+                let candidate = WithdrawalFee {
+                    currency: coin,
+                    amount: available,
+                    fee: fee.fee,
+                };
+                let net_amount = candidate.net_amount();
+                if net_amount >= min_net_amount && net_amount > rust_decimal::Decimal::ZERO {
+                    Some(candidate)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub struct ConvertPair {
+        pub from: coin::Coin,
+        pub to: coin::Coin,
+        pub min_amount: Option<rust_decimal::Decimal>,
+        pub max_amount: Option<rust_decimal::Decimal>,
+    }
+
+    impl TryFrom<super::ConvertPair> for ConvertPair {
+        type Error = String;
+
+        fn try_from(from: super::ConvertPair) -> Result<ConvertPair, String> {
+            Ok(ConvertPair {
+                from: coin::Coin::from(from.from_currency.as_str()),
+                to: coin::Coin::from(from.to_currency.as_str()),
+                min_amount: from.min_amount.as_deref().map(to_decimal).transpose()?,
+                max_amount: from.max_amount.as_deref().map(to_decimal).transpose()?,
+            })
+        }
+    }
+
+    pub struct ConvertQuote {
+        pub id: String,
+        pub from: coin::Coin,
+        pub to: coin::Coin,
+        pub from_amount: rust_decimal::Decimal,
+        pub to_amount: rust_decimal::Decimal,
+        pub rate: rust_decimal::Decimal,
+        pub expire_time: Option<String>,
+    }
+
+    impl TryFrom<super::ConvertQuote> for ConvertQuote {
+        type Error = String;
+
+        fn try_from(from: super::ConvertQuote) -> Result<ConvertQuote, String> {
+            Ok(ConvertQuote {
+                from_amount: to_decimal(&from.from_amount)?,
+                to_amount: to_decimal(&from.to_amount)?,
+                rate: to_decimal(&from.rate)?,
+                id: from.id,
+                from: coin::Coin::from(from.from_currency.as_str()),
+                to: coin::Coin::from(from.to_currency.as_str()),
+                expire_time: from.expire_time,
+            })
+        }
+    }
+
+    fn to_decimal(value: &str) -> Result<rust_decimal::Decimal, String> {
+        rust_decimal::Decimal::from_str(value)
+            .map_err(|error| format!("Bad decimal {}: {:#?}", value, error))
+    }
+
+    #[derive(Clone, Debug)]
+    pub enum TransferRecipient {
+        Email(String),
+        Username(String),
+    }
+
+    impl TransferRecipient {
+        pub fn identifier(&self) -> &str {
+            match self {
+                TransferRecipient::Email(email) => email,
+                TransferRecipient::Username(username) => username,
+            }
+        }
+
+        fn by(&self) -> &'static str {
+            match self {
+                TransferRecipient::Email(_) => "email",
+                TransferRecipient::Username(_) => "username",
+            }
+        }
+    }
+
+    pub struct CreateInternalTransfer {
+        currency: coin::Coin,
+        amount: f64,
+        recipient: TransferRecipient,
+    }
+
+    impl CreateInternalTransfer {
+        pub fn new(
+            currency: coin::Coin,
+            amount: f64,
+            recipient: TransferRecipient,
+        ) -> CreateInternalTransfer {
+            CreateInternalTransfer {
+                currency,
+                amount,
+                recipient,
+            }
+        }
+
+        pub fn recipient(&self) -> &TransferRecipient {
+            &self.recipient
+        }
+
+        pub fn to_model(self) -> super::CreateInternalTransfer {
+            super::CreateInternalTransfer {
+                currency: self.currency.to_string(),
+                amount: format!("{}", self.amount),
+                by: self.recipient.by().to_owned(),
+                identifier: self.recipient.identifier().to_owned(),
+            }
+        }
+    }
+
+    #[derive(PartialEq, Clone, Copy, Debug)]
+    pub enum SubAccountTransferType {
+        ToSubAccount,
+        FromSubAccount,
+    }
+
+    impl SubAccountTransferType {
+        pub fn to_string(self) -> &'static str {
+            match self {
+                SubAccountTransferType::ToSubAccount => "transferToSub",
+                SubAccountTransferType::FromSubAccount => "transferFromSub",
+            }
+        }
+    }
+
+    pub struct CreateSubAccountTransfer {
+        sub_account_id: u64,
+        currency: coin::Coin,
+        amount: f64,
+        transfer_type: SubAccountTransferType,
+    }
+
+    impl CreateSubAccountTransfer {
+        pub fn new(
+            sub_account_id: u64,
+            currency: coin::Coin,
+            amount: f64,
+            transfer_type: SubAccountTransferType,
+        ) -> CreateSubAccountTransfer {
+            CreateSubAccountTransfer {
+                sub_account_id,
+                currency,
+                amount,
+                transfer_type,
+            }
+        }
+
+        pub fn to_model(self) -> super::CreateSubAccountTransfer {
+            super::CreateSubAccountTransfer {
+                sub_account_id: self.sub_account_id,
+                amount: format!("{}", self.amount),
+                currency: self.currency.to_string(),
+                transfer_type: self.transfer_type.to_string().to_owned(),
+            }
+        }
+    }
+
+    pub struct UpdateMarginAccount {
+        margin_balance: f64,
+        leverage: Option<f64>,
+        strict_validate: bool,
+    }
+
+    impl UpdateMarginAccount {
+        /// Keeps the current leverage of the account.
+        pub fn new(margin_balance: f64) -> UpdateMarginAccount {
+            UpdateMarginAccount {
+                margin_balance,
+                leverage: None,
+                strict_validate: false,
+            }
+        }
+
+        pub fn with_leverage(mut self, leverage: f64) -> UpdateMarginAccount {
+            self.leverage = Some(leverage);
+            self
+        }
+
+        pub fn with_strict_validate(mut self, strict_validate: bool) -> UpdateMarginAccount {
+            self.strict_validate = strict_validate;
+            self
+        }
+
+        pub fn to_model(self) -> super::UpdateMarginAccount {
+            super::UpdateMarginAccount {
+                margin_balance: format!("{}", self.margin_balance),
+                leverage: self.leverage.map(|leverage| format!("{}", leverage)),
+                strict_validate: self.strict_validate,
             }
         }
     }
@@ -258,7 +1236,7 @@ pub mod typed {
             side: crate::base::Side,
             orderbook: &std::collections::HashMap<String, super::OrderBookPage>
         ) -> Option<OrderBookPage> {
-            let symbol_as_string = symbol.clone().to_string();
+            let symbol_as_string = symbol.to_string();
             match orderbook.get(&symbol_as_string) {
                 Some(page) => {
                     let price_iterator = match side {
@@ -266,7 +1244,7 @@ pub mod typed {
                         crate::base::Side::Sell => page.ask.iter(),
                     };
                     let prices = price_iterator
-                        .map(|price| Price::from(price))
+                        .map(Price::from)
                         .collect();
                     Some(OrderBookPage {
                         symbol,
@@ -291,4 +1269,54 @@ pub mod typed {
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn symbol() -> coin::Symbol {
+            coin::Symbol::new(coin::Coin::BTC, coin::Coin::USDT)
+        }
+
+        #[test]
+        fn all_or_none_needs_two_orders() {
+            let orders = vec![ListedOrder::limit(symbol(), base::Side::Buy, 1.0, 100.0)];
+            assert!(CreateOrderList::all_or_none(orders).is_err());
+        }
+
+        #[test]
+        fn all_or_none_rejects_stop_orders() {
+            let orders = vec![
+                ListedOrder::limit(symbol(), base::Side::Buy, 1.0, 100.0),
+                ListedOrder::stop_market(symbol(), base::Side::Sell, 1.0, 90.0),
+            ];
+            assert!(CreateOrderList::all_or_none(orders).is_err());
+        }
+
+        #[test]
+        fn all_or_none_sends_fill_or_kill() {
+            let orders = vec![
+                ListedOrder::limit(symbol(), base::Side::Buy, 1.0, 100.0),
+                ListedOrder::market(coin::Symbol::new(coin::Coin::ETH, coin::Coin::USDT), base::Side::Sell, 2.0),
+            ];
+            let model = CreateOrderList::all_or_none(orders).unwrap().to_model();
+            assert_eq!(model.orders.len(), 2);
+            assert!(model.orders.iter().all(|order| order.time_in_force.as_deref() == Some("FOK")));
+        }
+
+        #[test]
+        fn cancel_pair_shares_symbol_and_side() {
+            let first = ListedOrder::limit(symbol(), base::Side::Sell, 1.0, 110.0);
+            let second = ListedOrder::stop_market(symbol(), base::Side::Sell, 1.0, 90.0);
+            assert!(check_cancel_pair(&first, &second).is_ok());
+            let other_side = ListedOrder::stop_market(symbol(), base::Side::Buy, 1.0, 90.0);
+            assert!(check_cancel_pair(&first, &other_side).is_err());
+            let other_symbol = ListedOrder::stop_market(
+                coin::Symbol::new(coin::Coin::ETH, coin::Coin::USDT),
+                base::Side::Sell,
+                1.0,
+                90.0);
+            assert!(check_cancel_pair(&first, &other_symbol).is_err());
+        }
+    }
 }
@@ -0,0 +1,385 @@
+use std::str::FromStr;
+use super::base;
+use super::client;
+use super::models;
+use super::trading_stream;
+
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum OrderStatus {
+    New,
+    Suspended,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Expired,
+    Rejected,
+}
+
+impl OrderStatus {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            OrderStatus::New => "new",
+            OrderStatus::Suspended => "suspended",
+            OrderStatus::PartiallyFilled => "partiallyFilled",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Canceled => "canceled",
+            OrderStatus::Expired => "expired",
+            OrderStatus::Rejected => "rejected",
+        }
+    }
+
+    /// No further updates are expected once an order reaches a final status.
+    pub fn is_final(self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Expired | OrderStatus::Rejected)
+    }
+
+    pub fn can_become(self, next: OrderStatus) -> bool {
+        match self {
+            OrderStatus::New => true,
+            OrderStatus::Suspended => next != OrderStatus::Rejected,
+            OrderStatus::PartiallyFilled => matches!(
+                next,
+                OrderStatus::PartiallyFilled
+                    | OrderStatus::Filled
+                    | OrderStatus::Canceled
+                    | OrderStatus::Expired),
+            OrderStatus::Filled
+            | OrderStatus::Canceled
+            | OrderStatus::Expired
+            | OrderStatus::Rejected => self == next,
+        }
+    }
+}
+
+impl FromStr for OrderStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<OrderStatus, String> {
+        match status {
+            "new" => Ok(OrderStatus::New),
+            "suspended" => Ok(OrderStatus::Suspended),
+            "partiallyFilled" => Ok(OrderStatus::PartiallyFilled),
+            "filled" => Ok(OrderStatus::Filled),
+            "canceled" => Ok(OrderStatus::Canceled),
+            "expired" => Ok(OrderStatus::Expired),
+            "rejected" => Ok(OrderStatus::Rejected),
+            other => Err(format!("Unknown order status {}", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TrackedOrder {
+    pub id: u64,
+    pub client_order_id: String,
+    pub symbol: String,
+    pub side: base::Side,
+    pub status: OrderStatus,
+    pub quantity: f64,
+    pub cum_quantity: f64,
+    /// Volume-weighted price of the fills with a known trade price. Only
+    /// execution reports carry it, fills first seen by a REST poll are left out.
+    pub average_price: Option<f64>,
+    /// Filled quantity `average_price` is based on.
+    pub priced_quantity: f64,
+    pub updated_at: Option<String>,
+}
+
+impl TrackedOrder {
+    pub fn new(order: &models::Order) -> Result<TrackedOrder, String> {
+        let mut tracked = TrackedOrder {
+            id: order.id,
+            client_order_id: order.client_order_id.clone(),
+            symbol: order.symbol.clone(),
+            side: parse_side(&order.side)?,
+            status: OrderStatus::New,
+            quantity: parse(&order.quantity)?,
+            cum_quantity: 0.0,
+            average_price: None,
+            priced_quantity: 0.0,
+            updated_at: None,
+        };
+        apply(&mut tracked, from_order(order))?;
+        Ok(tracked)
+    }
+
+    pub fn remaining_quantity(&self) -> f64 {
+        (self.quantity - self.cum_quantity).max(0.0)
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.status.is_final()
+    }
+}
+
+struct OrderUpdate<'a> {
+    status: &'a str,
+    quantity: &'a str,
+    cum_quantity: &'a str,
+    /// Trade price of the fill.
+    price: Option<&'a str>,
+    updated_at: Option<&'a String>,
+}
+
+/// Follows orders from creation to a final status, fed by polling or by
+/// WebSocket execution reports. Orders are keyed by client order id.
+#[derive(Default)]
+pub struct OrderTracker {
+    orders: std::sync::Mutex<
+        std::collections::HashMap<String, tokio::sync::watch::Sender<TrackedOrder>>
+    >,
+}
+
+impl OrderTracker {
+    const ORDERS: &'static str = "Order tracker state is poisoned";
+
+    pub fn new() -> OrderTracker {
+        OrderTracker::default()
+    }
+
+    /// Starts tracking an order returned by `BTCClient` order creation.
+    pub fn register(&self, order: &models::Order) -> Result<TrackedOrder, String> {
+        let tracked = TrackedOrder::new(order)?;
+        let (sender, _receiver) = tokio::sync::watch::channel(tracked.clone());
+        self.orders.lock()
+            .expect(Self::ORDERS)
+            .insert(order.client_order_id.clone(), sender);
+        Ok(tracked)
+    }
+
+    pub fn get(&self, client_order_id: &str) -> Option<TrackedOrder> {
+        self.orders.lock()
+            .expect(Self::ORDERS)
+            .get(client_order_id)
+            .map(|sender| sender.borrow().clone())
+    }
+
+    pub fn orders(&self) -> Vec<TrackedOrder> {
+        self.orders.lock()
+            .expect(Self::ORDERS)
+            .values()
+            .map(|sender| sender.borrow().clone())
+            .collect()
+    }
+
+    /// Stops tracking an order, pending `wait_for_final` calls fail.
+    pub fn remove(&self, client_order_id: &str) -> Option<TrackedOrder> {
+        self.orders.lock()
+            .expect(Self::ORDERS)
+            .remove(client_order_id)
+            .map(|sender| sender.borrow().clone())
+    }
+
+    /// Applies an order from `BTCClient::get_order_by_id` or `get_active_orders`.
+    pub fn update_from_order(&self, order: &models::Order) -> Result<TrackedOrder, String> {
+        self.update(&order.client_order_id, from_order(order))
+    }
+
+    pub fn update_from_report(
+        &self,
+        report: &trading_stream::ExecutionReport,
+    ) -> Result<TrackedOrder, String> {
+        let update = OrderUpdate {
+            status: &report.status,
+            quantity: &report.quantity,
+            cum_quantity: &report.quantity_cumulative,
+            price: report.trade_price.as_deref(),
+            updated_at: report.updated_at.as_ref(),
+        };
+        self.update(&report.client_order_id, update)
+    }
+
+    /// Fetches the order once and applies it.
+    pub async fn poll<TConnector>(
+        &self,
+        client: &client::BTCClient<TConnector>,
+        client_order_id: &str,
+    ) -> Result<TrackedOrder, String>
+    where
+        TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
+    {
+        let order = client.get_order_by_id(client_order_id, None).await?;
+        self.update_from_order(&order)
+    }
+
+    /// Resolves once the order reaches a final status.
+    pub async fn wait_for_final(
+        &self,
+        client_order_id: &str,
+        timeout: std::time::Duration,
+    ) -> Result<TrackedOrder, String> {
+        let mut receiver = match self.orders.lock().expect(Self::ORDERS).get(client_order_id) {
+            Some(sender) => sender.subscribe(),
+            None => return Err(format!("Order {} is not tracked", client_order_id)),
+        };
+        let wait = async {
+            loop {
+                {
+                    let order = receiver.borrow_and_update();
+                    if order.is_final() {
+                        return Ok(order.clone());
+                    }
+                }
+                if receiver.changed().await.is_err() {
+                    return Err(format!("Order {} is no longer tracked", client_order_id));
+                }
+            }
+        };
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(result) => result,
+            Err(_) => Err(format!("Timed out waiting for order {}", client_order_id)),
+        }
+    }
+
+    fn update(&self, client_order_id: &str, update: OrderUpdate<'_>) -> Result<TrackedOrder, String> {
+        let orders = self.orders.lock().expect(Self::ORDERS);
+        let sender = match orders.get(client_order_id) {
+            Some(sender) => sender,
+            None => return Err(format!("Order {} is not tracked", client_order_id)),
+        };
+        let mut tracked = sender.borrow().clone();
+        apply(&mut tracked, update)?;
+        sender.send_replace(tracked.clone());
+        Ok(tracked)
+    }
+}
+
+fn from_order(order: &models::Order) -> OrderUpdate<'_> {
+    OrderUpdate {
+        status: &order.status,
+        quantity: &order.quantity,
+        cum_quantity: &order.cim_quantity,
+        price: None,
+        updated_at: order.updated_at.as_ref(),
+    }
+}
+
+fn apply(tracked: &mut TrackedOrder, update: OrderUpdate<'_>) -> Result<(), String> {
+    let status = OrderStatus::from_str(update.status)?;
+    if !tracked.status.can_become(status) {
+        return Err(format!(
+            "Order {} can not change status from {} to {}",
+            tracked.client_order_id,
+            tracked.status.to_string(),
+            status.to_string()));
+    }
+    let cum_quantity = parse(update.cum_quantity)?;
+    if cum_quantity < tracked.cum_quantity {
+        return Err(format!(
+            "Order {} filled quantity decreased from {} to {}",
+            tracked.client_order_id,
+            tracked.cum_quantity,
+            cum_quantity));
+    }
+    let filled = cum_quantity - tracked.cum_quantity;
+    if filled > 0.0 {
+        if let Some(price) = update.price {
+            let price = parse(price)?;
+            let notional = tracked.average_price.unwrap_or(0.0) * tracked.priced_quantity + filled * price;
+            tracked.priced_quantity += filled;
+            tracked.average_price = Some(notional / tracked.priced_quantity);
+        }
+    }
+    tracked.status = status;
+    tracked.quantity = parse(update.quantity)?;
+    tracked.cum_quantity = cum_quantity;
+    if let Some(updated_at) = update.updated_at {
+        tracked.updated_at = Some(updated_at.clone());
+    }
+    Ok(())
+}
+
+fn parse_side(side: &str) -> Result<base::Side, String> {
+    match side {
+        "buy" => Ok(base::Side::Buy),
+        "sell" => Ok(base::Side::Sell),
+        other => Err(format!("Unknown order side {}", other)),
+    }
+}
+
+fn parse(value: &str) -> Result<f64, String> {
+    f64::from_str(value)
+        .map_err(|error| format!("Bad number {}: {:#?}", value, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracked() -> TrackedOrder {
+        TrackedOrder {
+            id: 1,
+            client_order_id: "order".to_owned(),
+            symbol: "ETHBTC".to_owned(),
+            side: base::Side::Buy,
+            status: OrderStatus::New,
+            quantity: 3.0,
+            cum_quantity: 0.0,
+            average_price: None,
+            priced_quantity: 0.0,
+            updated_at: None,
+        }
+    }
+
+    fn update<'a>(status: &'a str, cum_quantity: &'a str, price: Option<&'a str>) -> OrderUpdate<'a> {
+        OrderUpdate {
+            status,
+            quantity: "3",
+            cum_quantity,
+            price,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn allows_forward_transitions() {
+        assert!(OrderStatus::New.can_become(OrderStatus::PartiallyFilled));
+        assert!(OrderStatus::New.can_become(OrderStatus::Rejected));
+        assert!(OrderStatus::Suspended.can_become(OrderStatus::New));
+        assert!(OrderStatus::PartiallyFilled.can_become(OrderStatus::Filled));
+        assert!(OrderStatus::PartiallyFilled.can_become(OrderStatus::Canceled));
+        assert!(OrderStatus::Filled.can_become(OrderStatus::Filled));
+    }
+
+    #[test]
+    fn rejects_backward_transitions() {
+        assert!(!OrderStatus::Suspended.can_become(OrderStatus::Rejected));
+        assert!(!OrderStatus::PartiallyFilled.can_become(OrderStatus::New));
+        assert!(!OrderStatus::Filled.can_become(OrderStatus::PartiallyFilled));
+        assert!(!OrderStatus::Canceled.can_become(OrderStatus::Filled));
+    }
+
+    #[test]
+    fn applies_fills_until_final() {
+        let mut order = tracked();
+        apply(&mut order, update("partiallyFilled", "1", Some("10"))).unwrap();
+        assert!(!order.is_final());
+        assert_eq!(order.remaining_quantity(), 2.0);
+        apply(&mut order, update("filled", "3", Some("13"))).unwrap();
+        assert!(order.is_final());
+        assert_eq!(order.average_price, Some(12.0));
+        assert!(apply(&mut order, update("canceled", "3", None)).is_err());
+        assert_eq!(order.status, OrderStatus::Filled);
+    }
+
+    #[test]
+    fn rejects_decreasing_fill() {
+        let mut order = tracked();
+        apply(&mut order, update("partiallyFilled", "2", Some("10"))).unwrap();
+        assert!(apply(&mut order, update("partiallyFilled", "1", Some("10"))).is_err());
+        assert_eq!(order.cum_quantity, 2.0);
+    }
+
+    #[test]
+    fn averages_priced_fills_only() {
+        let mut order = tracked();
+        apply(&mut order, update("partiallyFilled", "1", None)).unwrap();
+        assert_eq!(order.average_price, None);
+        apply(&mut order, update("filled", "3", Some("10"))).unwrap();
+        assert_eq!(order.average_price, Some(10.0));
+        assert_eq!(order.priced_quantity, 2.0);
+    }
+}
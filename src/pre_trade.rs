@@ -0,0 +1,489 @@
+use std::str::FromStr;
+use super::base;
+use super::coin;
+use super::models;
+use super::symbol_registry;
+
+/// User-defined caps checked on top of the exchange symbol limits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RiskLimits {
+    /// Maximal order value in quote currency.
+    pub max_notional: Option<f64>,
+    /// Maximal distance of the order price from the mid price, e.g. `0.05` for 5%.
+    pub max_price_deviation: Option<f64>,
+    /// Whether the order must be covered by the available balance.
+    pub check_balance: bool,
+}
+
+impl RiskLimits {
+    pub fn new() -> RiskLimits {
+        RiskLimits::default()
+    }
+
+    pub fn with_max_notional(mut self, max_notional: f64) -> RiskLimits {
+        self.max_notional = Some(max_notional);
+        self
+    }
+
+    pub fn with_max_price_deviation(mut self, max_price_deviation: f64) -> RiskLimits {
+        self.max_price_deviation = Some(max_price_deviation);
+        self
+    }
+
+    pub fn with_balance_check(mut self) -> RiskLimits {
+        self.check_balance = true;
+        self
+    }
+}
+
+/// Market state an order is checked against.
+#[derive(Clone, Debug, Default)]
+pub struct MarketContext {
+    /// Trading balance the order is checked against, required by `RiskLimits::check_balance`.
+    pub balance: Option<models::Balance>,
+    pub mid_price: Option<f64>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Violation {
+    UnknownSymbol(String),
+    /// Quantity is not a finite number.
+    InvalidQuantity(f64),
+    QuantityBelowMinimum {
+        quantity: f64,
+        minimum: rust_decimal::Decimal,
+    },
+    QuantityNotMultiple {
+        quantity: f64,
+        increment: rust_decimal::Decimal,
+    },
+    NonPositivePrice(f64),
+    PriceNotMultiple {
+        price: f64,
+        tick_size: rust_decimal::Decimal,
+    },
+    /// Market order value can not be estimated without a mid price.
+    MissingReferencePrice,
+    /// Balance check is requested but the market context carries no balance,
+    /// as for margin orders.
+    MissingBalance,
+    InsufficientBalance {
+        currency: coin::Coin,
+        required: f64,
+        available: f64,
+    },
+    MaxNotionalExceeded {
+        notional: f64,
+        max_notional: f64,
+    },
+    MaxPriceDeviationExceeded {
+        price: f64,
+        mid_price: f64,
+        deviation: f64,
+        max_deviation: f64,
+    },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::UnknownSymbol(symbol) =>
+                write!(formatter, "Unknown symbol {}", symbol),
+            Violation::InvalidQuantity(quantity) =>
+                write!(formatter, "Quantity {} is not a valid number", quantity),
+            Violation::QuantityBelowMinimum { quantity, minimum } =>
+                write!(formatter, "Quantity {} is less than minimal quantity {}", quantity, minimum),
+            Violation::QuantityNotMultiple { quantity, increment } =>
+                write!(formatter, "Quantity {} is not a multiple of {}", quantity, increment),
+            Violation::NonPositivePrice(price) =>
+                write!(formatter, "Price {} must be positive", price),
+            Violation::PriceNotMultiple { price, tick_size } =>
+                write!(formatter, "Price {} is not a multiple of tick size {}", price, tick_size),
+            Violation::MissingReferencePrice =>
+                write!(formatter, "No mid price to estimate the order value"),
+            Violation::MissingBalance =>
+                write!(formatter, "No balance to check the order against"),
+            Violation::InsufficientBalance { currency, required, available } =>
+                write!(formatter, "Order requires {} {}, available {}", required, currency, available),
+            Violation::MaxNotionalExceeded { notional, max_notional } =>
+                write!(formatter, "Order value {} exceeds maximum {}", notional, max_notional),
+            Violation::MaxPriceDeviationExceeded { price, mid_price, deviation, max_deviation } =>
+                write!(
+                    formatter,
+                    "Price {} deviates {} from mid price {}, maximum {}",
+                    price,
+                    deviation,
+                    mid_price,
+                    max_deviation),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidationReport {
+    pub symbol: coin::Symbol,
+    pub side: base::Side,
+    pub quantity: f64,
+    /// Limit price, or the mid price for market orders.
+    pub price: Option<f64>,
+    /// Order value in quote currency.
+    pub notional: Option<f64>,
+    pub fee_rate: Option<f64>,
+    /// Expected fee in quote currency.
+    pub fee: Option<f64>,
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<ValidationReport, String> {
+        if self.is_ok() {
+            return Ok(self);
+        }
+        let violations = self.violations.iter()
+            .map(|violation| violation.to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+        Err(format!("Pre-trade validation of {} failed: {}", self.symbol, violations))
+    }
+}
+
+/// Checks orders before they are sent, collecting every violation instead of
+/// stopping at the first one.
+#[derive(Clone, Debug)]
+pub struct PreTradeValidator {
+    symbol_registry: std::sync::Arc<symbol_registry::SymbolRegistry>,
+    limits: RiskLimits,
+    fee_rate: Option<f64>,
+}
+
+impl PreTradeValidator {
+    pub fn new(
+        symbol_registry: std::sync::Arc<symbol_registry::SymbolRegistry>,
+        limits: RiskLimits,
+    ) -> PreTradeValidator {
+        PreTradeValidator {
+            symbol_registry,
+            limits,
+            fee_rate: None,
+        }
+    }
+
+    /// Account fee rate from `BTCClient::get_trading_commission`,
+    /// the symbol default taker rate is used otherwise.
+    pub fn with_fee_rate(mut self, fee_rate: f64) -> PreTradeValidator {
+        self.fee_rate = Some(fee_rate);
+        self
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    /// Whether `MarketContext::balance` is used.
+    pub fn needs_balance(&self) -> bool {
+        self.limits.check_balance
+    }
+
+    /// Whether `MarketContext::mid_price` is used for an order with `price`.
+    pub fn needs_mid_price(&self, price: Option<f64>) -> bool {
+        price.is_none() || self.limits.max_price_deviation.is_some()
+    }
+
+    pub fn validate_market_order(
+        &self,
+        order: &models::typed::CreateMarketOrder,
+        context: &MarketContext,
+    ) -> ValidationReport {
+        self.validate(order.symbol(), order.side(), order.quantity(), None, context)
+    }
+
+    pub fn validate_limit_order(
+        &self,
+        order: &models::typed::CreateLimitOrder,
+        context: &MarketContext,
+    ) -> ValidationReport {
+        self.validate(
+            order.symbol(),
+            order.side(),
+            order.quantity(),
+            Some(order.price()),
+            context)
+    }
+
+    fn validate(
+        &self,
+        symbol: &coin::Symbol,
+        side: base::Side,
+        quantity: f64,
+        price: Option<f64>,
+        context: &MarketContext,
+    ) -> ValidationReport {
+        let mut report = ValidationReport {
+            symbol: symbol.clone(),
+            side,
+            quantity,
+            price: price.or(context.mid_price),
+            notional: None,
+            fee_rate: self.fee_rate,
+            fee: None,
+            violations: Vec::new(),
+        };
+        let info = match self.symbol_registry.get(symbol) {
+            Some(info) => info,
+            None => {
+                report.violations.push(Violation::UnknownSymbol(symbol.to_string()));
+                return report;
+            },
+        };
+        report.fee_rate = Some(self.fee_rate.unwrap_or(info.take_liquidity_rate));
+        check_quantity(info, quantity, &mut report.violations);
+        if let Some(price) = price {
+            check_price(info, price, &mut report.violations);
+        }
+        if let (Some(price), Some(mid_price), Some(max_deviation)) =
+            (price, context.mid_price, self.limits.max_price_deviation)
+        {
+            let deviation = (price - mid_price).abs() / mid_price;
+            if deviation > max_deviation {
+                report.violations.push(Violation::MaxPriceDeviationExceeded {
+                    price,
+                    mid_price,
+                    deviation,
+                    max_deviation,
+                });
+            }
+        }
+        if self.limits.check_balance && context.balance.is_none() {
+            report.violations.push(Violation::MissingBalance);
+        }
+        let reference_price = match report.price {
+            Some(reference_price) => reference_price,
+            None => {
+                if self.limits.max_notional.is_some() || context.balance.is_some() {
+                    report.violations.push(Violation::MissingReferencePrice);
+                }
+                return report;
+            },
+        };
+        let notional = quantity * reference_price;
+        let fee = notional * report.fee_rate.unwrap_or(0.0);
+        report.notional = Some(notional);
+        report.fee = Some(fee);
+        if let Some(max_notional) = self.limits.max_notional {
+            if notional > max_notional {
+                report.violations.push(Violation::MaxNotionalExceeded {
+                    notional,
+                    max_notional,
+                });
+            }
+        }
+        if let Some(balance) = &context.balance {
+            let (currency, required) = match side {
+                base::Side::Buy => (&info.quote_currency, notional + fee),
+                base::Side::Sell => (&info.base_currency, quantity),
+            };
+            let available = balance.iter()
+                .find(|balance| &balance.currency == currency)
+                .and_then(|balance| f64::from_str(&balance.available).ok())
+                .unwrap_or(0.0);
+            if required > available {
+                report.violations.push(Violation::InsufficientBalance {
+                    currency: coin::Coin::from(currency.as_str()),
+                    required,
+                    available,
+                });
+            }
+        }
+        report
+    }
+}
+
+fn check_quantity(
+    info: &symbol_registry::SymbolInfo,
+    quantity: f64,
+    violations: &mut Vec<Violation>,
+) {
+    let decimal = match symbol_registry::from_f64(quantity) {
+        Ok(decimal) => decimal,
+        Err(_) => {
+            violations.push(Violation::InvalidQuantity(quantity));
+            return;
+        },
+    };
+    if decimal < info.quantity_increment {
+        violations.push(Violation::QuantityBelowMinimum {
+            quantity,
+            minimum: info.quantity_increment,
+        });
+    } else if !symbol_registry::is_multiple(decimal, info.quantity_increment) {
+        violations.push(Violation::QuantityNotMultiple {
+            quantity,
+            increment: info.quantity_increment,
+        });
+    }
+}
+
+fn check_price(
+    info: &symbol_registry::SymbolInfo,
+    price: f64,
+    violations: &mut Vec<Violation>,
+) {
+    let decimal = match symbol_registry::from_f64(price) {
+        Ok(decimal) if decimal > rust_decimal::Decimal::ZERO => decimal,
+        _ => {
+            violations.push(Violation::NonPositivePrice(price));
+            return;
+        },
+    };
+    if !symbol_registry::is_multiple(decimal, info.tick_size) {
+        violations.push(Violation::PriceNotMultiple {
+            price,
+            tick_size: info.tick_size,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol() -> coin::Symbol {
+        coin::Symbol::new(coin::Coin::BTC, coin::Coin::USDT)
+    }
+
+    fn validator(limits: RiskLimits) -> PreTradeValidator {
+        let registry = symbol_registry::SymbolRegistry::new(vec![models::Symbol {
+            id: "BTCUSDT".to_owned(),
+            base_currency: "BTC".to_owned(),
+            quote_currency: "USDT".to_owned(),
+            quantity_increment: "0.001".to_owned(),
+            tick_size: "0.01".to_owned(),
+            take_liquidity_rate: "0.001".to_owned(),
+            provide_liquidity_rate: "0.0005".to_owned(),
+            fee_currency: "USDT".to_owned(),
+        }]).unwrap();
+        PreTradeValidator::new(std::sync::Arc::new(registry), limits)
+    }
+
+    fn balance(currency: &str, available: &str) -> Option<models::Balance> {
+        Some(vec![models::AccountCurrency {
+            currency: currency.to_owned(),
+            available: available.to_owned(),
+            reserved: "0".to_owned(),
+        }])
+    }
+
+    fn limit(quantity: f64, price: f64) -> models::typed::CreateLimitOrder {
+        models::typed::CreateLimitOrder::new(symbol(), base::Side::Buy, quantity, price)
+    }
+
+    fn market(quantity: f64) -> models::typed::CreateMarketOrder {
+        models::typed::CreateMarketOrder::new(symbol(), base::Side::Buy, quantity)
+    }
+
+    #[test]
+    fn accepts_valid_order() {
+        let report = validator(RiskLimits::new().with_balance_check())
+            .validate_limit_order(&limit(0.01, 100.0), &MarketContext {
+                balance: balance("USDT", "10"),
+                mid_price: None,
+            });
+        assert!(report.is_ok(), "{:?}", report.violations);
+        assert_eq!(report.notional, Some(1.0));
+    }
+
+    #[test]
+    fn reports_unknown_symbol() {
+        let order = models::typed::CreateMarketOrder::new(
+            coin::Symbol::new(coin::Coin::ETH, coin::Coin::USDT),
+            base::Side::Buy,
+            1.0);
+        let report = validator(RiskLimits::new()).validate_market_order(&order, &MarketContext::default());
+        assert_eq!(report.violations, vec![Violation::UnknownSymbol("ETHUSDT".to_owned())]);
+    }
+
+    #[test]
+    fn reports_invalid_quantity() {
+        let report = validator(RiskLimits::new())
+            .validate_limit_order(&limit(f64::NAN, 100.0), &MarketContext::default());
+        assert!(matches!(report.violations[..], [Violation::InvalidQuantity(quantity)] if quantity.is_nan()));
+    }
+
+    #[test]
+    fn reports_quantity_below_minimum() {
+        let report = validator(RiskLimits::new())
+            .validate_limit_order(&limit(0.0005, 100.0), &MarketContext::default());
+        assert!(matches!(report.violations[..], [Violation::QuantityBelowMinimum { .. }]));
+    }
+
+    #[test]
+    fn reports_quantity_not_multiple() {
+        let report = validator(RiskLimits::new())
+            .validate_limit_order(&limit(0.0015, 100.0), &MarketContext::default());
+        assert!(matches!(report.violations[..], [Violation::QuantityNotMultiple { .. }]));
+    }
+
+    #[test]
+    fn reports_non_positive_price() {
+        let report = validator(RiskLimits::new())
+            .validate_limit_order(&limit(0.01, -1.0), &MarketContext::default());
+        assert_eq!(report.violations, vec![Violation::NonPositivePrice(-1.0)]);
+    }
+
+    #[test]
+    fn reports_price_not_multiple() {
+        let report = validator(RiskLimits::new())
+            .validate_limit_order(&limit(0.01, 100.005), &MarketContext::default());
+        assert!(matches!(report.violations[..], [Violation::PriceNotMultiple { .. }]));
+    }
+
+    #[test]
+    fn reports_missing_reference_price() {
+        let report = validator(RiskLimits::new().with_max_notional(10.0))
+            .validate_market_order(&market(0.01), &MarketContext::default());
+        assert_eq!(report.violations, vec![Violation::MissingReferencePrice]);
+    }
+
+    #[test]
+    fn reports_missing_balance() {
+        let report = validator(RiskLimits::new().with_balance_check())
+            .validate_limit_order(&limit(0.01, 100.0), &MarketContext::default());
+        assert_eq!(report.violations, vec![Violation::MissingBalance]);
+    }
+
+    #[test]
+    fn reports_insufficient_balance() {
+        let report = validator(RiskLimits::new().with_balance_check())
+            .validate_limit_order(&limit(0.01, 100.0), &MarketContext {
+                balance: balance("USDT", "1"),
+                mid_price: None,
+            });
+        assert!(matches!(
+            &report.violations[..],
+            [Violation::InsufficientBalance { currency, .. }] if *currency == coin::Coin::USDT));
+    }
+
+    #[test]
+    fn reports_max_notional_exceeded() {
+        let report = validator(RiskLimits::new().with_max_notional(5.0))
+            .validate_market_order(&market(0.1), &MarketContext {
+                balance: None,
+                mid_price: Some(100.0),
+            });
+        assert!(matches!(report.violations[..], [Violation::MaxNotionalExceeded { .. }]));
+    }
+
+    #[test]
+    fn reports_max_price_deviation_exceeded() {
+        let report = validator(RiskLimits::new().with_max_price_deviation(0.05))
+            .validate_limit_order(&limit(0.01, 110.0), &MarketContext {
+                balance: None,
+                mid_price: Some(100.0),
+            });
+        assert!(matches!(report.violations[..], [Violation::MaxPriceDeviationExceeded { .. }]));
+    }
+}
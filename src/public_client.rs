@@ -73,7 +73,7 @@ where
                         accumulator.push(',');
                         accumulator
                     });
-            if symbols.len() > 0 {
+            if !symbols.is_empty() {
                 let comma = symbols.pop();
                 assert_eq!(comma, Some(','));
             }
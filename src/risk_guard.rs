@@ -0,0 +1,591 @@
+use std::str::FromStr;
+use super::base;
+use super::client;
+use super::coin;
+use super::models;
+use super::order;
+use super::trading_stream;
+
+/// Value limits are keyed by quote currency, orders of symbols quoted in a
+/// currency without a limit are not checked against it.
+#[derive(Clone, Debug, Default)]
+pub struct RiskGuardLimits {
+    pub max_open_orders: Option<usize>,
+    /// Maximal value of all open orders quoted in the currency, the new one included.
+    pub max_notional: std::collections::HashMap<coin::Coin, f64>,
+    pub max_orders_per_minute: Option<usize>,
+    /// Maximal realized loss in the currency since the start of the UTC day, as a positive number.
+    pub max_daily_loss: std::collections::HashMap<coin::Coin, f64>,
+}
+
+impl RiskGuardLimits {
+    pub fn new() -> RiskGuardLimits {
+        RiskGuardLimits::default()
+    }
+
+    pub fn with_max_open_orders(mut self, max_open_orders: usize) -> RiskGuardLimits {
+        self.max_open_orders = Some(max_open_orders);
+        self
+    }
+
+    pub fn with_max_notional(mut self, quote: coin::Coin, max_notional: f64) -> RiskGuardLimits {
+        self.max_notional.insert(quote, max_notional);
+        self
+    }
+
+    pub fn with_max_orders_per_minute(mut self, max_orders_per_minute: usize) -> RiskGuardLimits {
+        self.max_orders_per_minute = Some(max_orders_per_minute);
+        self
+    }
+
+    pub fn with_max_daily_loss(mut self, quote: coin::Coin, max_daily_loss: f64) -> RiskGuardLimits {
+        self.max_daily_loss.insert(quote, max_daily_loss);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+struct OpenOrder {
+    symbol: coin::Symbol,
+    side: base::Side,
+    quantity: f64,
+    cum_quantity: f64,
+    /// Limit price, or the mid price at creation for market orders.
+    price: Option<f64>,
+}
+
+/// Slot taken by an order between the limit check and the exchange response.
+#[derive(Debug)]
+struct Reservation {
+    order: OpenOrder,
+    sent: std::time::Instant,
+}
+
+/// Net base position of a symbol built from fills, signed, and its average entry price.
+#[derive(Clone, Copy, Debug, Default)]
+struct Holding {
+    quantity: f64,
+    average_price: f64,
+}
+
+#[derive(Debug, Default)]
+struct GuardState {
+    open_orders: std::collections::HashMap<String, OpenOrder>,
+    reserved: std::collections::HashMap<u64, Reservation>,
+    next_reservation: u64,
+    positions: std::collections::HashMap<coin::Coin, f64>,
+    holdings: std::collections::HashMap<coin::Symbol, Holding>,
+    sent: std::collections::VecDeque<std::time::Instant>,
+    day: Option<chrono::NaiveDate>,
+    daily_pnl: std::collections::HashMap<coin::Coin, f64>,
+    halted: Option<String>,
+}
+
+/// Wraps `BTCClient` spot trading with exposure limits and a kill switch.
+/// On the first breach new orders are blocked and all open orders are canceled
+/// until `reset` is called.
+pub struct RiskGuard<TConnector> {
+    client: client::BTCClient<TConnector>,
+    limits: RiskGuardLimits,
+    state: std::sync::Mutex<GuardState>,
+}
+
+impl<TConnector> RiskGuard<TConnector>
+where
+    TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
+{
+    const STATE: &'static str = "Risk guard state is poisoned";
+
+    pub fn new(client: client::BTCClient<TConnector>, limits: RiskGuardLimits) -> RiskGuard<TConnector> {
+        RiskGuard {
+            client,
+            limits,
+            state: std::sync::Mutex::new(GuardState::default()),
+        }
+    }
+
+    pub fn client(&self) -> &client::BTCClient<TConnector> {
+        &self.client
+    }
+
+    pub fn limits(&self) -> &RiskGuardLimits {
+        &self.limits
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.state.lock().expect(Self::STATE).halted.is_some()
+    }
+
+    pub fn halt_reason(&self) -> Option<String> {
+        self.state.lock().expect(Self::STATE).halted.clone()
+    }
+
+    /// Allows new orders again after a breach.
+    pub fn reset(&self) {
+        self.state.lock().expect(Self::STATE).halted = None;
+    }
+
+    /// Orders tracked as open, those still being created included.
+    pub fn open_orders(&self) -> usize {
+        let state = self.state.lock().expect(Self::STATE);
+        state.open_orders.len() + state.reserved.len()
+    }
+
+    /// Net position change of `coin` caused by fills seen by the guard.
+    pub fn position(&self, coin: &coin::Coin) -> f64 {
+        let state = self.state.lock().expect(Self::STATE);
+        state.positions.get(coin).copied().unwrap_or(0.0)
+    }
+
+    pub fn positions(&self) -> std::collections::HashMap<coin::Coin, f64> {
+        self.state.lock().expect(Self::STATE).positions.clone()
+    }
+
+    /// Realized result in `quote` since the start of the UTC day.
+    pub fn daily_pnl(&self, quote: &coin::Coin) -> f64 {
+        let mut state = self.state.lock().expect(Self::STATE);
+        roll_day(&mut state);
+        state.daily_pnl.get(quote).copied().unwrap_or(0.0)
+    }
+
+    pub async fn create_market_order(
+        &self,
+        order: models::typed::CreateMarketOrder,
+    ) -> Result<models::Order, String> {
+        let price = if self.limits.max_notional.contains_key(&order.symbol().right) {
+            match self.client.get_mid_price(order.symbol()).await? {
+                Some(price) => Some(price),
+                None => return Err(format!("No mid price of {} to check the order value", order.symbol())),
+            }
+        } else {
+            None
+        };
+        let open_order = OpenOrder {
+            symbol: order.symbol().clone(),
+            side: order.side(),
+            quantity: order.quantity(),
+            cum_quantity: 0.0,
+            price,
+        };
+        let reservation = self.check(open_order).await?;
+        let created = self.client.create_market_order(order).await;
+        self.track(reservation, created).await
+    }
+
+    pub async fn create_limit_order(
+        &self,
+        order: models::typed::CreateLimitOrder,
+    ) -> Result<models::Order, String> {
+        let open_order = OpenOrder {
+            symbol: order.symbol().clone(),
+            side: order.side(),
+            quantity: order.quantity(),
+            cum_quantity: 0.0,
+            price: Some(order.price()),
+        };
+        let reservation = self.check(open_order).await?;
+        let created = self.client.create_limit_order(order).await;
+        self.track(reservation, created).await
+    }
+
+    pub async fn cancel_order_by_id(&self, id: &str) -> Result<models::Order, String> {
+        let order = self.client.cancel_order_by_id(id).await?;
+        self.update_order(&order).await?;
+        Ok(order)
+    }
+
+    pub async fn cancel_all_orders(
+        &self,
+        symbol: Option<coin::Symbol>,
+    ) -> Result<models::Orders, String> {
+        let orders = self.client.cancel_all_orders(symbol).await?;
+        for order in orders.iter() {
+            self.apply_order(order)?;
+        }
+        Ok(orders)
+    }
+
+    /// Applies an order polled with `BTCClient::get_order_by_id` or `get_active_orders`.
+    /// REST orders carry no trade price, fills are valued at the order price.
+    pub async fn update_order(&self, order: &models::Order) -> Result<(), String> {
+        match self.apply_order(order)? {
+            Some(reason) => self.trip(reason).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Fees are taken to be charged in the quote currency.
+    pub async fn apply_execution_report(&self, report: &trading_stream::ExecutionReport) -> Result<(), String> {
+        let cum_quantity = parse(&report.quantity_cumulative)?;
+        let price = match &report.trade_price {
+            Some(price) => Some(parse(price)?),
+            None => report.price.as_deref().map(parse).transpose()?,
+        };
+        let fee = report.trade_fee.as_deref().map(parse).transpose()?.unwrap_or(0.0);
+        match self.on_update(&report.client_order_id, &report.status, cum_quantity, price, fee) {
+            Some(reason) => self.trip(reason).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Adds profit, or loss if negative, in `quote` to the daily result on top of
+    /// what is realized from the tracked fills, e.g. funding or fills of other sessions.
+    /// A loss beyond `max_daily_loss` trips the kill switch.
+    pub async fn record_pnl(&self, quote: &coin::Coin, pnl: f64) -> Result<(), String> {
+        let breach = {
+            let mut state = self.state.lock().expect(Self::STATE);
+            self.add_pnl(&mut state, quote, pnl)
+        };
+        match breach {
+            Some(reason) => self.trip(reason).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the limits and reserves a slot for `order` under the same lock,
+    /// so concurrent orders can not pass the limits together.
+    async fn check(&self, order: OpenOrder) -> Result<u64, String> {
+        let breach = {
+            let mut state = self.state.lock().expect(Self::STATE);
+            if let Some(reason) = &state.halted {
+                return Err(format!("Trading is halted: {}", reason));
+            }
+            roll_day(&mut state);
+            match self.find_breach(&mut state, &order) {
+                Some(reason) => reason,
+                None => {
+                    let id = state.next_reservation;
+                    state.next_reservation += 1;
+                    let sent = std::time::Instant::now();
+                    state.sent.push_back(sent);
+                    state.reserved.insert(id, Reservation { order, sent });
+                    return Ok(id);
+                },
+            }
+        };
+        self.trip(breach.clone()).await?;
+        Err(format!("Trading is halted: {}", breach))
+    }
+
+    fn find_breach(&self, state: &mut GuardState, order: &OpenOrder) -> Option<String> {
+        if let Some(max_open_orders) = self.limits.max_open_orders {
+            if state.open_orders.len() + state.reserved.len() + 1 > max_open_orders {
+                return Some(format!("Open orders limit {} reached", max_open_orders));
+            }
+        }
+        if let Some(max_orders_per_minute) = self.limits.max_orders_per_minute {
+            let now = std::time::Instant::now();
+            while let Some(sent) = state.sent.front() {
+                if now.duration_since(*sent) < std::time::Duration::from_secs(60) {
+                    break;
+                }
+                state.sent.pop_front();
+            }
+            if state.sent.len() + 1 > max_orders_per_minute {
+                return Some(format!("Orders per minute limit {} reached", max_orders_per_minute));
+            }
+        }
+        let quote = &order.symbol.right;
+        if let Some(max_notional) = self.limits.max_notional.get(quote) {
+            let notional = state.open_orders.values()
+                .chain(state.reserved.values().map(|reservation| &reservation.order))
+                .chain(std::iter::once(order))
+                .filter(|order| &order.symbol.right == quote)
+                .map(|order| (order.quantity - order.cum_quantity) * order.price.unwrap_or(0.0))
+                .sum::<f64>();
+            if notional > *max_notional {
+                return Some(format!(
+                    "Open orders value {} {} exceeds maximum {}",
+                    notional,
+                    quote,
+                    max_notional));
+            }
+        }
+        for (quote, max_daily_loss) in self.limits.max_daily_loss.iter() {
+            let loss = -state.daily_pnl.get(quote).copied().unwrap_or(0.0);
+            if loss > *max_daily_loss {
+                return Some(format!("Daily loss {} {} exceeds maximum {}", loss, quote, max_daily_loss));
+            }
+        }
+        None
+    }
+
+    /// Blocks new orders and cancels every open order of the account in all markets.
+    pub async fn trip(&self, reason: String) -> Result<(), String> {
+        self.state.lock().expect(Self::STATE).halted = Some(reason);
+        let mut errors = Vec::new();
+        if let Err(error) = self.cancel_all_orders(None).await {
+            errors.push(error);
+        }
+        for market in [base::Market::Margin, base::Market::Futures].iter() {
+            if let Err(error) = self.client.cancel_all_orders_in(*market, None).await {
+                errors.push(format!("{}: {}", market.to_string(), error));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Failed to cancel orders: {}", errors.join("; ")))
+        }
+    }
+
+    /// Turns the reservation into an open order, or releases it if the order was not created.
+    async fn track(
+        &self,
+        reservation: u64,
+        created: Result<models::Order, String>,
+    ) -> Result<models::Order, String> {
+        {
+            let mut state = self.state.lock().expect(Self::STATE);
+            let reservation = match state.reserved.remove(&reservation) {
+                Some(reservation) => reservation,
+                None => return created,
+            };
+            match &created {
+                Ok(created) => {
+                    state.open_orders.insert(created.client_order_id.clone(), reservation.order);
+                },
+                Err(_) => {
+                    if let Some(index) = state.sent.iter().position(|sent| *sent == reservation.sent) {
+                        state.sent.remove(index);
+                    }
+                },
+            }
+        }
+        let created = created?;
+        if let Err(error) = self.update_order(&created).await {
+            log::error!("Failed to apply created order {}: {}", created.client_order_id, error);
+        }
+        Ok(created)
+    }
+
+    /// Returns the breach reason if the update tripped the daily loss limit.
+    fn apply_order(&self, order: &models::Order) -> Result<Option<String>, String> {
+        let cum_quantity = parse(&order.cim_quantity)?;
+        let price = order.price.as_deref().map(parse).transpose()?;
+        Ok(self.on_update(&order.client_order_id, &order.status, cum_quantity, price, 0.0))
+    }
+
+    fn on_update(
+        &self,
+        client_order_id: &str,
+        status: &str,
+        cum_quantity: f64,
+        price: Option<f64>,
+        fee: f64,
+    ) -> Option<String> {
+        let mut state = self.state.lock().expect(Self::STATE);
+        let order = state.open_orders.get_mut(client_order_id)?;
+        let filled = cum_quantity - order.cum_quantity;
+        order.cum_quantity = cum_quantity;
+        let order = order.clone();
+        if order::OrderStatus::from_str(status).is_ok_and(order::OrderStatus::is_final) {
+            state.open_orders.remove(client_order_id);
+        }
+        if filled <= 0.0 {
+            return None;
+        }
+        let price = price.or(order.price).unwrap_or(0.0);
+        let (base, quote) = match order.side {
+            base::Side::Buy => (filled, -filled * price - fee),
+            base::Side::Sell => (-filled, filled * price - fee),
+        };
+        *state.positions.entry(order.symbol.left.clone()).or_insert(0.0) += base;
+        *state.positions.entry(order.symbol.right.clone()).or_insert(0.0) += quote;
+        let holding = state.holdings.entry(order.symbol.clone()).or_default();
+        let pnl = realize(holding, base, price) - fee;
+        self.add_pnl(&mut state, &order.symbol.right, pnl)
+    }
+
+    /// Returns the breach reason if the result crossed the daily loss limit just now.
+    fn add_pnl(&self, state: &mut GuardState, quote: &coin::Coin, pnl: f64) -> Option<String> {
+        roll_day(state);
+        let daily_pnl = state.daily_pnl.entry(quote.clone()).or_insert(0.0);
+        *daily_pnl += pnl;
+        let loss = -*daily_pnl;
+        match self.limits.max_daily_loss.get(quote) {
+            Some(max_daily_loss) if loss > *max_daily_loss && state.halted.is_none() => {
+                let reason = format!("Daily loss {} {} exceeds maximum {}", loss, quote, max_daily_loss);
+                state.halted = Some(reason.clone());
+                Some(reason)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Applies a fill of signed `quantity` at `price`, returns the profit realized
+/// by the part closing the current holding.
+fn realize(holding: &mut Holding, quantity: f64, price: f64) -> f64 {
+    if holding.quantity == 0.0 || holding.quantity.signum() == quantity.signum() {
+        let total = holding.quantity.abs() + quantity.abs();
+        holding.average_price =
+            (holding.average_price * holding.quantity.abs() + price * quantity.abs()) / total;
+        holding.quantity += quantity;
+        return 0.0;
+    }
+    let closed = quantity.abs().min(holding.quantity.abs());
+    let pnl = closed * (price - holding.average_price) * holding.quantity.signum();
+    holding.quantity += quantity;
+    if holding.quantity.abs() < f64::EPSILON * closed.max(1.0) {
+        *holding = Holding::default();
+    } else if holding.quantity.signum() == quantity.signum() {
+        holding.average_price = price;
+    }
+    pnl
+}
+
+fn roll_day(state: &mut GuardState) {
+    let today = chrono::Utc::now().date_naive();
+    if state.day != Some(today) {
+        state.day = Some(today);
+        state.daily_pnl.clear();
+    }
+}
+
+fn parse(value: &str) -> Result<f64, String> {
+    f64::from_str(value)
+        .map_err(|error| format!("Bad number {}: {:#?}", value, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(limits: RiskGuardLimits) -> RiskGuard<hyper::client::HttpConnector> {
+        let auth_context = crate::context::AuthContext::new(
+            "key".to_owned(),
+            "secret".to_owned(),
+            url::Url::parse("https://api.hitbtc.com/api/2/").unwrap());
+        let client = client::BTCClient::new(
+            std::sync::Arc::new(hyper::Client::new()),
+            std::sync::Arc::new(auth_context));
+        RiskGuard::new(client, limits)
+    }
+
+    fn open_order(quote: coin::Coin, side: base::Side, quantity: f64, price: f64) -> OpenOrder {
+        OpenOrder {
+            symbol: coin::Symbol::new(coin::Coin::BTC, quote),
+            side,
+            quantity,
+            cum_quantity: 0.0,
+            price: Some(price),
+        }
+    }
+
+    #[test]
+    fn find_breach_counts_open_and_reserved_orders() {
+        let guard = guard(RiskGuardLimits::new().with_max_open_orders(2));
+        let mut state = GuardState::default();
+        let order = open_order(coin::Coin::USDT, base::Side::Buy, 1.0, 100.0);
+        state.open_orders.insert("a".to_owned(), order.clone());
+        assert!(guard.find_breach(&mut state, &order).is_none());
+        state.reserved.insert(0, Reservation { order: order.clone(), sent: std::time::Instant::now() });
+        assert!(guard.find_breach(&mut state, &order).is_some());
+    }
+
+    #[test]
+    fn find_breach_limits_orders_per_minute() {
+        let guard = guard(RiskGuardLimits::new().with_max_orders_per_minute(2));
+        let mut state = GuardState::default();
+        let order = open_order(coin::Coin::USDT, base::Side::Buy, 1.0, 100.0);
+        state.sent.push_back(std::time::Instant::now());
+        assert!(guard.find_breach(&mut state, &order).is_none());
+        state.sent.push_back(std::time::Instant::now());
+        assert!(guard.find_breach(&mut state, &order).is_some());
+    }
+
+    #[test]
+    fn find_breach_keys_notional_by_quote() {
+        let guard = guard(RiskGuardLimits::new().with_max_notional(coin::Coin::USDT, 1000.0));
+        let mut state = GuardState::default();
+        state.open_orders.insert("a".to_owned(), open_order(coin::Coin::ETH, base::Side::Buy, 100.0, 100.0));
+        state.open_orders.insert("b".to_owned(), open_order(coin::Coin::USDT, base::Side::Buy, 5.0, 100.0));
+        let order = open_order(coin::Coin::USDT, base::Side::Sell, 5.0, 100.0);
+        assert!(guard.find_breach(&mut state, &order).is_none());
+        let order = open_order(coin::Coin::USDT, base::Side::Sell, 6.0, 100.0);
+        assert!(guard.find_breach(&mut state, &order).is_some());
+        let order = open_order(coin::Coin::ETH, base::Side::Sell, 100.0, 100.0);
+        assert!(guard.find_breach(&mut state, &order).is_none());
+    }
+
+    #[test]
+    fn find_breach_stops_after_daily_loss() {
+        let guard = guard(RiskGuardLimits::new().with_max_daily_loss(coin::Coin::USDT, 50.0));
+        let mut state = GuardState::default();
+        let order = open_order(coin::Coin::ETH, base::Side::Buy, 1.0, 100.0);
+        state.daily_pnl.insert(coin::Coin::USDT, -50.0);
+        assert!(guard.find_breach(&mut state, &order).is_none());
+        state.daily_pnl.insert(coin::Coin::USDT, -51.0);
+        assert!(guard.find_breach(&mut state, &order).is_some());
+    }
+
+    #[tokio::test]
+    async fn check_reserves_slot_until_tracked() {
+        let guard = guard(RiskGuardLimits::new().with_max_open_orders(1).with_max_orders_per_minute(5));
+        let reservation = guard.check(open_order(coin::Coin::USDT, base::Side::Buy, 1.0, 100.0)).await.unwrap();
+        assert_eq!(guard.open_orders(), 1);
+        let created = guard.track(reservation, Err("rejected".to_owned())).await;
+        assert!(created.is_err());
+        assert_eq!(guard.open_orders(), 0);
+        assert!(guard.state.lock().unwrap().sent.is_empty());
+        guard.check(open_order(coin::Coin::USDT, base::Side::Buy, 1.0, 100.0)).await.unwrap();
+        assert_eq!(guard.open_orders(), 1);
+    }
+
+    #[test]
+    fn fills_realize_pnl() {
+        let guard = guard(RiskGuardLimits::new());
+        {
+            let mut state = guard.state.lock().unwrap();
+            state.open_orders.insert("buy".to_owned(), open_order(coin::Coin::USDT, base::Side::Buy, 2.0, 100.0));
+            state.open_orders.insert("sell".to_owned(), open_order(coin::Coin::USDT, base::Side::Sell, 2.0, 90.0));
+        }
+        assert!(guard.on_update("buy", "filled", 2.0, Some(100.0), 1.0).is_none());
+        assert_eq!(guard.daily_pnl(&coin::Coin::USDT), -1.0);
+        assert!(guard.on_update("sell", "partiallyFilled", 1.0, Some(90.0), 0.0).is_none());
+        assert_eq!(guard.daily_pnl(&coin::Coin::USDT), -11.0);
+        assert_eq!(guard.position(&coin::Coin::BTC), 1.0);
+        assert_eq!(guard.open_orders(), 1);
+    }
+
+    #[test]
+    fn fills_trip_daily_loss() {
+        let guard = guard(RiskGuardLimits::new().with_max_daily_loss(coin::Coin::USDT, 5.0));
+        {
+            let mut state = guard.state.lock().unwrap();
+            state.open_orders.insert("buy".to_owned(), open_order(coin::Coin::USDT, base::Side::Buy, 1.0, 100.0));
+            state.open_orders.insert("sell".to_owned(), open_order(coin::Coin::USDT, base::Side::Sell, 1.0, 90.0));
+        }
+        assert!(guard.on_update("buy", "filled", 1.0, None, 0.0).is_none());
+        assert!(guard.on_update("sell", "filled", 1.0, None, 0.0).is_some());
+        assert!(guard.is_halted());
+    }
+
+    #[test]
+    fn daily_loss_resets_on_new_day() {
+        let guard = guard(RiskGuardLimits::new().with_max_daily_loss(coin::Coin::USDT, 5.0));
+        {
+            let mut state = guard.state.lock().unwrap();
+            state.day = Some(chrono::Utc::now().date_naive() - chrono::Duration::days(1));
+            state.daily_pnl.insert(coin::Coin::USDT, -10.0);
+        }
+        assert_eq!(guard.daily_pnl(&coin::Coin::USDT), 0.0);
+        let mut state = guard.state.lock().unwrap();
+        let order = open_order(coin::Coin::USDT, base::Side::Buy, 1.0, 100.0);
+        assert!(guard.find_breach(&mut state, &order).is_none());
+    }
+
+    #[test]
+    fn realize_handles_flipping_position() {
+        let mut holding = Holding::default();
+        assert_eq!(realize(&mut holding, 1.0, 100.0), 0.0);
+        assert_eq!(realize(&mut holding, 1.0, 200.0), 0.0);
+        assert_eq!(holding.average_price, 150.0);
+        assert_eq!(realize(&mut holding, -3.0, 160.0), 20.0);
+        assert_eq!(holding.quantity, -1.0);
+        assert_eq!(holding.average_price, 160.0);
+        assert_eq!(realize(&mut holding, 1.0, 150.0), 10.0);
+        assert_eq!(holding.quantity, 0.0);
+    }
+}
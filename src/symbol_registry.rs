@@ -0,0 +1,271 @@
+use std::str::FromStr;
+use super::base;
+use super::coin;
+use super::models;
+use super::public_client;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Rounding {
+    Floor,
+    Ceil,
+    /// Halves are rounded away from zero.
+    Nearest,
+}
+
+#[derive(Clone, Debug)]
+pub struct SymbolInfo {
+    pub id: String,
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub quantity_increment: rust_decimal::Decimal,
+    pub tick_size: rust_decimal::Decimal,
+    pub take_liquidity_rate: f64,
+    pub provide_liquidity_rate: f64,
+    pub fee_currency: String,
+}
+
+impl SymbolInfo {
+    pub fn new(symbol: models::Symbol) -> Result<SymbolInfo, String> {
+        Ok(SymbolInfo {
+            quantity_increment: to_decimal(&symbol.quantity_increment)?,
+            tick_size: to_decimal(&symbol.tick_size)?,
+            take_liquidity_rate: to_f64(&symbol.take_liquidity_rate)?,
+            provide_liquidity_rate: to_f64(&symbol.provide_liquidity_rate)?,
+            id: symbol.id,
+            base_currency: symbol.base_currency,
+            quote_currency: symbol.quote_currency,
+            fee_currency: symbol.fee_currency,
+        })
+    }
+
+    pub fn round_price(&self, price: f64, rounding: Rounding) -> Result<f64, String> {
+        round(price, self.tick_size, rounding)
+    }
+
+    pub fn round_quantity(&self, quantity: f64, rounding: Rounding) -> Result<f64, String> {
+        round(quantity, self.quantity_increment, rounding)
+    }
+
+    pub fn validate_price(&self, price: f64) -> Result<(), String> {
+        let decimal = from_f64(price)?;
+        if decimal <= rust_decimal::Decimal::ZERO {
+            return Err(format!("Price {} of {} must be positive", price, self.id));
+        }
+        if !is_multiple(decimal, self.tick_size) {
+            return Err(format!(
+                "Price {} of {} is not a multiple of tick size {}",
+                price,
+                self.id,
+                self.tick_size));
+        }
+        Ok(())
+    }
+
+    pub fn validate_quantity(&self, quantity: f64) -> Result<(), String> {
+        let decimal = from_f64(quantity)?;
+        if decimal < self.quantity_increment {
+            return Err(format!(
+                "Quantity {} of {} is less than minimal quantity {}",
+                quantity,
+                self.id,
+                self.quantity_increment));
+        }
+        if !is_multiple(decimal, self.quantity_increment) {
+            return Err(format!(
+                "Quantity {} of {} is not a multiple of quantity increment {}",
+                quantity,
+                self.id,
+                self.quantity_increment));
+        }
+        Ok(())
+    }
+
+    /// Rounding that never makes an order of `side` trade at a worse price.
+    pub fn passive_price_rounding(side: base::Side) -> Rounding {
+        match side {
+            base::Side::Buy => Rounding::Floor,
+            base::Side::Sell => Rounding::Ceil,
+        }
+    }
+}
+
+/// Symbol metadata keyed by exchange symbol id.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolRegistry {
+    symbols: std::collections::HashMap<String, SymbolInfo>,
+}
+
+impl SymbolRegistry {
+    pub fn new(symbols: models::Symbols) -> Result<SymbolRegistry, String> {
+        let symbols = symbols.into_iter()
+            .map(|symbol| SymbolInfo::new(symbol).map(|info| (info.id.clone(), info)))
+            .collect::<Result<_, String>>()?;
+        Ok(SymbolRegistry { symbols })
+    }
+
+    pub async fn load<TConnector>(
+        client: &public_client::PublicClient<TConnector>,
+    ) -> Result<SymbolRegistry, String>
+    where
+        TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
+    {
+        SymbolRegistry::new(client.get_all_symbols().await?)
+    }
+
+    pub fn get(&self, symbol: &coin::Symbol) -> Option<&SymbolInfo> {
+        self.symbols.get(&symbol.to_string())
+    }
+
+    pub fn get_by_id(&self, id: &str) -> Option<&SymbolInfo> {
+        self.symbols.get(id)
+    }
+
+    /// Splits an exchange symbol id such as `ETHBTC` into base and quote coins.
+    pub fn resolve(&self, id: &str) -> Option<coin::Symbol> {
+        self.symbols.get(id)
+            .map(|info| coin::Symbol::new(
+                coin::Coin::from(info.base_currency.as_str()),
+                coin::Coin::from(info.quote_currency.as_str())))
+    }
+
+    /// Falls back to `Symbol::from_str` for ids missing in the registry.
+    pub fn parse(&self, id: &str) -> Result<coin::Symbol, String> {
+        match self.resolve(id) {
+            Some(symbol) => Ok(symbol),
+            None => id.parse(),
+        }
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &SymbolInfo> {
+        self.symbols.values()
+    }
+
+    /// Floors the quantity to the lot size and rejects orders smaller than one lot.
+    pub fn round_market_order(
+        &self,
+        order: models::typed::CreateMarketOrder,
+    ) -> Result<models::typed::CreateMarketOrder, String> {
+        let info = self.info(order.symbol())?;
+        let quantity = info.round_quantity(order.quantity(), Rounding::Floor)?;
+        info.validate_quantity(quantity)?;
+        Ok(order.with_quantity(quantity))
+    }
+
+    /// Floors the quantity to the lot size and rounds the price to the tick size
+    /// without making it worse for the order side.
+    pub fn round_limit_order(
+        &self,
+        order: models::typed::CreateLimitOrder,
+    ) -> Result<models::typed::CreateLimitOrder, String> {
+        let info = self.info(order.symbol())?;
+        let quantity = info.round_quantity(order.quantity(), Rounding::Floor)?;
+        info.validate_quantity(quantity)?;
+        let rounding = SymbolInfo::passive_price_rounding(order.side());
+        let price = info.round_price(order.price(), rounding)?;
+        info.validate_price(price)?;
+        Ok(order.with_quantity(quantity).with_price(price))
+    }
+
+    fn info(&self, symbol: &coin::Symbol) -> Result<&SymbolInfo, String> {
+        match self.get(symbol) {
+            Some(info) => Ok(info),
+            None => Err(format!("Unknown symbol: {}", symbol)),
+        }
+    }
+}
+
+fn round(
+    value: f64,
+    step: rust_decimal::Decimal,
+    rounding: Rounding,
+) -> Result<f64, String> {
+    if step <= rust_decimal::Decimal::ZERO {
+        return Err(format!("Bad step {}", step));
+    }
+    let steps = from_f64(value)? / step;
+    let steps = match rounding {
+        Rounding::Floor => steps.floor(),
+        Rounding::Ceil => steps.ceil(),
+        Rounding::Nearest => steps.round_dp_with_strategy(
+            0,
+            rust_decimal::RoundingStrategy::MidpointAwayFromZero),
+    };
+    to_f64(&(steps * step).normalize().to_string())
+}
+
+pub(crate) fn is_multiple(value: rust_decimal::Decimal, step: rust_decimal::Decimal) -> bool {
+    !step.is_zero() && (value % step).is_zero()
+}
+
+pub(crate) fn from_f64(value: f64) -> Result<rust_decimal::Decimal, String> {
+    to_decimal(&value.to_string())
+}
+
+fn to_decimal(value: &str) -> Result<rust_decimal::Decimal, String> {
+    rust_decimal::Decimal::from_str(value)
+        .map_err(|error| format!("Bad decimal {}: {:#?}", value, error))
+}
+
+fn to_f64(value: &str) -> Result<f64, String> {
+    f64::from_str(value)
+        .map_err(|error| format!("Bad number {}: {:#?}", value, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> SymbolInfo {
+        SymbolInfo {
+            id: "ETHBTC".to_owned(),
+            base_currency: "ETH".to_owned(),
+            quote_currency: "BTC".to_owned(),
+            quantity_increment: to_decimal("0.001").unwrap(),
+            tick_size: to_decimal("0.5").unwrap(),
+            take_liquidity_rate: 0.0025,
+            provide_liquidity_rate: 0.001,
+            fee_currency: "BTC".to_owned(),
+        }
+    }
+
+    #[test]
+    fn rounds_price_to_tick_size() {
+        let info = info();
+        assert_eq!(info.round_price(10.7, Rounding::Floor), Ok(10.5));
+        assert_eq!(info.round_price(10.2, Rounding::Ceil), Ok(10.5));
+        assert_eq!(info.round_price(10.2, Rounding::Nearest), Ok(10.0));
+        assert_eq!(info.round_price(10.5, Rounding::Ceil), Ok(10.5));
+    }
+
+    #[test]
+    fn rounds_nearest_halves_away_from_zero() {
+        let info = info();
+        assert_eq!(info.round_price(10.25, Rounding::Nearest), Ok(10.5));
+        assert_eq!(info.round_price(10.75, Rounding::Nearest), Ok(11.0));
+        assert_eq!(info.round_quantity(0.0025, Rounding::Nearest), Ok(0.003));
+    }
+
+    #[test]
+    fn rounds_quantity_to_lot_size() {
+        let info = info();
+        assert_eq!(info.round_quantity(1.23456, Rounding::Floor), Ok(1.234));
+        assert_eq!(info.round_quantity(1.2341, Rounding::Ceil), Ok(1.235));
+    }
+
+    #[test]
+    fn validates_tick_and_lot() {
+        let info = info();
+        assert!(info.validate_price(10.5).is_ok());
+        assert!(info.validate_price(10.3).is_err());
+        assert!(info.validate_price(0.0).is_err());
+        assert!(info.validate_quantity(0.002).is_ok());
+        assert!(info.validate_quantity(0.0015).is_err());
+        assert!(info.validate_quantity(0.0005).is_err());
+    }
+
+    #[test]
+    fn passive_rounding_never_worsens_price() {
+        assert_eq!(SymbolInfo::passive_price_rounding(base::Side::Buy), Rounding::Floor);
+        assert_eq!(SymbolInfo::passive_price_rounding(base::Side::Sell), Rounding::Ceil);
+    }
+}
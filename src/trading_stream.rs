@@ -0,0 +1,164 @@
+use super::context;
+use super::models;
+use super::websocket;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ExecutionReport {
+    pub id: u64,
+    pub client_order_id: String,
+    pub symbol: String,
+    pub side: String,
+    pub status: String,
+    #[serde(rename="type")]
+    pub order_type: String,
+    pub time_in_force: String,
+    pub quantity: String,
+    pub quantity_cumulative: String,
+    pub price: Option<String>,
+    #[serde(default)]
+    pub post_only: bool,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub report_type: String,
+    pub original_client_order_id: Option<String>,
+    pub trade_id: Option<u64>,
+    pub trade_quantity: Option<String>,
+    pub trade_price: Option<String>,
+    pub trade_fee: Option<String>,
+}
+
+/// Authenticated trading session over WebSocket.
+pub struct TradingClient {
+    connection: websocket::Connection,
+}
+
+impl TradingClient {
+    const ORDER_REPORT: &'static str = "spot_order";
+    const ORDER_SNAPSHOT: &'static str = "spot_orders";
+
+    pub async fn connect(
+        url: url::Url,
+        auth_context: std::sync::Arc<context::AuthContext>,
+    ) -> Result<TradingClient, String> {
+        TradingClient::connect_with(url, auth_context, websocket::ReconnectPolicy::default()).await
+    }
+
+    pub async fn connect_with(
+        url: url::Url,
+        auth_context: std::sync::Arc<context::AuthContext>,
+        policy: websocket::ReconnectPolicy,
+    ) -> Result<TradingClient, String> {
+        let connection = websocket::Connection::connect_with(url, policy).await?;
+        connection.login(auth_context).await?;
+        Ok(TradingClient { connection })
+    }
+
+    pub async fn create_market_order(
+        &self,
+        order: models::typed::CreateMarketOrder,
+        client_order_id: Option<String>,
+    ) -> Result<ExecutionReport, String> {
+        let order = order.to_model();
+        let mut params = serde_json::json!({
+            "symbol": order.symbol,
+            "side": order.side,
+            "type": order.order_type,
+            "quantity": order.quantity,
+        });
+        if let Some(client_order_id) = client_order_id {
+            params["client_order_id"] = client_order_id.into();
+        }
+        self.send_order("spot_new_order", params).await
+    }
+
+    pub async fn create_limit_order(
+        &self,
+        order: models::typed::CreateLimitOrder,
+        client_order_id: Option<String>,
+    ) -> Result<ExecutionReport, String> {
+        let order = order.to_model();
+        let mut params = serde_json::json!({
+            "symbol": order.symbol,
+            "side": order.side,
+            "quantity": order.quantity,
+            "price": order.price,
+        });
+        if let Some(client_order_id) = client_order_id {
+            params["client_order_id"] = client_order_id.into();
+        }
+        self.send_order("spot_new_order", params).await
+    }
+
+    pub async fn replace_order(
+        &self,
+        client_order_id: &str,
+        new_client_order_id: &str,
+        quantity: f64,
+        price: Option<f64>,
+    ) -> Result<ExecutionReport, String> {
+        let mut params = serde_json::json!({
+            "client_order_id": client_order_id,
+            "new_client_order_id": new_client_order_id,
+            "quantity": format!("{}", quantity),
+        });
+        if let Some(price) = price {
+            params["price"] = format!("{}", price).into();
+        }
+        self.send_order("spot_replace_order", params).await
+    }
+
+    pub async fn cancel_order(
+        &self,
+        client_order_id: &str,
+    ) -> Result<ExecutionReport, String> {
+        let params = serde_json::json!({ "client_order_id": client_order_id });
+        self.send_order("spot_cancel_order", params).await
+    }
+
+    /// Execution reports for all orders of the account, starting with the active ones.
+    /// After a reconnect the active orders are reported again.
+    pub async fn subscribe_execution_reports(
+        &self,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<ExecutionReport>>, String> {
+        use futures::StreamExt;
+        let snapshots = self.connection.listen(Self::ORDER_SNAPSHOT)?;
+        let reports = self.connection.listen(Self::ORDER_REPORT)?;
+        self.connection.call_subscribe(
+            "spot_subscribe",
+            serde_json::json!({}),
+            &[Self::ORDER_SNAPSHOT, Self::ORDER_REPORT]).await?;
+        let snapshots = snapshots
+            .flat_map(|notification| {
+                let events = match websocket::read_params::<Vec<ExecutionReport>>(notification) {
+                    Some(websocket::StreamEvent::Event(reports)) => reports.into_iter()
+                        .map(websocket::StreamEvent::Event)
+                        .collect(),
+                    Some(websocket::StreamEvent::Reconnected) => vec![websocket::StreamEvent::Reconnected],
+                    Some(websocket::StreamEvent::LoginFailed(error)) => vec![websocket::StreamEvent::LoginFailed(error)],
+                    Some(websocket::StreamEvent::RestoreFailed(error)) => vec![websocket::StreamEvent::RestoreFailed(error)],
+                    _ => Vec::new(),
+                };
+                futures::stream::iter(events)
+            });
+        let reports = reports
+            .filter_map(|notification| async move {
+                match notification {
+                    websocket::Notification::Message(_) => websocket::read_params(notification),
+                    _ => None,
+                }
+            });
+        Ok(futures::stream::select(snapshots, reports))
+    }
+
+    async fn send_order(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<ExecutionReport, String> {
+        let result = self.connection.call(method, params).await?;
+        match websocket::read_value(result) {
+            Some(report) => Ok(report),
+            None => Err("Deserialization error".to_owned()),
+        }
+    }
+}
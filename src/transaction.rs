@@ -0,0 +1,219 @@
+use super::coin;
+use super::models;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TransactionType {
+    Payout,
+    Payin,
+    Deposit,
+    Withdraw,
+    BankToExchange,
+    ExchangeToBank,
+}
+
+impl TransactionType {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            TransactionType::Payout => "payout",
+            TransactionType::Payin => "payin",
+            TransactionType::Deposit => "deposit",
+            TransactionType::Withdraw => "withdraw",
+            TransactionType::BankToExchange => "bankToExchange",
+            TransactionType::ExchangeToBank => "exchangeToBank",
+        }
+    }
+}
+
+impl std::str::FromStr for TransactionType {
+    type Err = String;
+
+    fn from_str(transaction_type: &str) -> Result<TransactionType, String> {
+        match transaction_type {
+            "payout" => Ok(TransactionType::Payout),
+            "payin" => Ok(TransactionType::Payin),
+            "deposit" => Ok(TransactionType::Deposit),
+            "withdraw" => Ok(TransactionType::Withdraw),
+            "bankToExchange" => Ok(TransactionType::BankToExchange),
+            "exchangeToBank" => Ok(TransactionType::ExchangeToBank),
+            other => Err(format!("Unknown transaction type {}", other)),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TransactionStatus {
+    Created,
+    Pending,
+    Failed,
+    Success,
+}
+
+impl TransactionStatus {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            TransactionStatus::Created => "created",
+            TransactionStatus::Pending => "pending",
+            TransactionStatus::Failed => "failed",
+            TransactionStatus::Success => "success",
+        }
+    }
+
+    pub fn is_final(self) -> bool {
+        matches!(self, TransactionStatus::Failed | TransactionStatus::Success)
+    }
+}
+
+impl std::str::FromStr for TransactionStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<TransactionStatus, String> {
+        match status {
+            "created" => Ok(TransactionStatus::Created),
+            "pending" => Ok(TransactionStatus::Pending),
+            "failed" => Ok(TransactionStatus::Failed),
+            "success" => Ok(TransactionStatus::Success),
+            other => Err(format!("Unknown transaction status {}", other)),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TransactionOrderBy {
+    Timestamp,
+    Index,
+}
+
+impl TransactionOrderBy {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            TransactionOrderBy::Timestamp => "timestamp",
+            TransactionOrderBy::Index => "index",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Sort {
+    Asc,
+    Desc,
+}
+
+impl Sort {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            Sort::Asc => "ASC",
+            Sort::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Range {
+    Time(
+        Option<chrono::DateTime<chrono::Utc>>,
+        Option<chrono::DateTime<chrono::Utc>>,
+    ),
+    Index(Option<u64>, Option<u64>),
+}
+
+/// Filter of `BTCClient::get_transactions`. The endpoint filters by a single
+/// currency and by either a time or an index range; several currencies, types
+/// and statuses are filtered on the returned pages. The history endpoint does
+/// not report subtypes, those are only known from `wallet_stream::TransactionUpdate`.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionFilter {
+    currencies: Vec<coin::Coin>,
+    types: Vec<TransactionType>,
+    statuses: Vec<TransactionStatus>,
+    range: Option<Range>,
+    sort: Option<Sort>,
+}
+
+impl TransactionFilter {
+    pub const MAX_LIMIT: u64 = 1000;
+
+    pub fn new() -> TransactionFilter {
+        TransactionFilter::default()
+    }
+
+    pub fn with_currencies(mut self, currencies: Vec<coin::Coin>) -> TransactionFilter {
+        self.currencies = currencies;
+        self
+    }
+
+    pub fn with_types(mut self, types: Vec<TransactionType>) -> TransactionFilter {
+        self.types = types;
+        self
+    }
+
+    pub fn with_statuses(mut self, statuses: Vec<TransactionStatus>) -> TransactionFilter {
+        self.statuses = statuses;
+        self
+    }
+
+    /// Replaces an index range.
+    pub fn with_time_range(
+        mut self,
+        from: Option<chrono::DateTime<chrono::Utc>>,
+        till: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> TransactionFilter {
+        self.range = Some(Range::Time(from, till));
+        self
+    }
+
+    /// Range of `Transaction::index`, replaces a time range.
+    pub fn with_index_range(
+        mut self,
+        index_from: Option<u64>,
+        index_till: Option<u64>,
+    ) -> TransactionFilter {
+        self.range = Some(Range::Index(index_from, index_till));
+        self
+    }
+
+    pub fn with_sort(mut self, sort: Sort) -> TransactionFilter {
+        self.sort = Some(sort);
+        self
+    }
+
+    pub fn append_to(&self, url: &mut url::Url) {
+        let mut query = url.query_pairs_mut();
+        if let [currency] = self.currencies.as_slice() {
+            query.append_pair("currency", currency.id());
+        }
+        match &self.range {
+            Some(Range::Time(from, till)) => {
+                query.append_pair("by", TransactionOrderBy::Timestamp.to_string());
+                if let Some(from) = from {
+                    query.append_pair("from", &from.to_rfc3339());
+                }
+                if let Some(till) = till {
+                    query.append_pair("till", &till.to_rfc3339());
+                }
+            },
+            Some(Range::Index(from, till)) => {
+                query.append_pair("by", TransactionOrderBy::Index.to_string());
+                if let Some(from) = from {
+                    query.append_pair("from", &format!("{}", from));
+                }
+                if let Some(till) = till {
+                    query.append_pair("till", &format!("{}", till));
+                }
+            },
+            None => {},
+        }
+        if let Some(sort) = self.sort {
+            query.append_pair("sort", sort.to_string());
+        }
+    }
+
+    /// Whether `transaction` passes the filters the endpoint does not apply.
+    pub fn matches(&self, transaction: &models::Transaction) -> bool {
+        (self.currencies.is_empty()
+            || self.currencies.iter().any(|currency| currency.id() == transaction.currency))
+            && (self.types.is_empty()
+                || transaction.transaction_type().is_ok_and(|t| self.types.contains(&t)))
+            && (self.statuses.is_empty()
+                || transaction.status().is_ok_and(|status| self.statuses.contains(&status)))
+    }
+}
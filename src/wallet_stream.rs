@@ -0,0 +1,195 @@
+use super::context;
+use super::models;
+use super::websocket;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct TransactionUpdate {
+    pub id: u64,
+    pub status: TransactionUpdateStatus,
+    #[serde(rename="type")]
+    pub transaction_type: TransactionUpdateType,
+    pub subtype: Option<TransactionUpdateSubtype>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub native: Option<NativeTransaction>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct NativeTransaction {
+    pub tx_id: String,
+    pub currency: String,
+    pub amount: String,
+    pub fee: Option<String>,
+    pub address: Option<String>,
+    pub payment_id: Option<String>,
+    pub hash: Option<String>,
+    pub confirmations: Option<u64>,
+}
+
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionUpdateType {
+    Deposit,
+    Withdraw,
+    Transfer,
+    Swap,
+}
+
+impl TransactionUpdateType {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            TransactionUpdateType::Deposit => "DEPOSIT",
+            TransactionUpdateType::Withdraw => "WITHDRAW",
+            TransactionUpdateType::Transfer => "TRANSFER",
+            TransactionUpdateType::Swap => "SWAP",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionUpdateSubtype {
+    Unclassified,
+    Blockchain,
+    Airdrop,
+    Affiliate,
+    Staking,
+    BuyCrypto,
+    Offchain,
+    Fiat,
+    SubAccount,
+    WalletToSpot,
+    SpotToWallet,
+    ChainSwitchFrom,
+    ChainSwitchTo,
+}
+
+impl TransactionUpdateSubtype {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            TransactionUpdateSubtype::Unclassified => "UNCLASSIFIED",
+            TransactionUpdateSubtype::Blockchain => "BLOCKCHAIN",
+            TransactionUpdateSubtype::Airdrop => "AIRDROP",
+            TransactionUpdateSubtype::Affiliate => "AFFILIATE",
+            TransactionUpdateSubtype::Staking => "STAKING",
+            TransactionUpdateSubtype::BuyCrypto => "BUY_CRYPTO",
+            TransactionUpdateSubtype::Offchain => "OFFCHAIN",
+            TransactionUpdateSubtype::Fiat => "FIAT",
+            TransactionUpdateSubtype::SubAccount => "SUB_ACCOUNT",
+            TransactionUpdateSubtype::WalletToSpot => "WALLET_TO_SPOT",
+            TransactionUpdateSubtype::SpotToWallet => "SPOT_TO_WALLET",
+            TransactionUpdateSubtype::ChainSwitchFrom => "CHAIN_SWITCH_FROM",
+            TransactionUpdateSubtype::ChainSwitchTo => "CHAIN_SWITCH_TO",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionUpdateStatus {
+    Created,
+    Pending,
+    Failed,
+    Success,
+    RolledBack,
+}
+
+impl TransactionUpdateStatus {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            TransactionUpdateStatus::Created => "CREATED",
+            TransactionUpdateStatus::Pending => "PENDING",
+            TransactionUpdateStatus::Failed => "FAILED",
+            TransactionUpdateStatus::Success => "SUCCESS",
+            TransactionUpdateStatus::RolledBack => "ROLLED_BACK",
+        }
+    }
+
+    pub fn is_final(self) -> bool {
+        matches!(
+            self,
+            TransactionUpdateStatus::Failed
+                | TransactionUpdateStatus::Success
+                | TransactionUpdateStatus::RolledBack)
+    }
+}
+
+/// Authenticated wallet notifications over WebSocket.
+pub struct WalletClient {
+    connection: websocket::Connection,
+}
+
+impl WalletClient {
+    const BALANCES: &'static str = "wallet_balances";
+    const BALANCE_UPDATE: &'static str = "wallet_balance_update";
+    const TRANSACTION_UPDATE: &'static str = "transaction_update";
+
+    pub async fn connect(
+        url: url::Url,
+        auth_context: std::sync::Arc<context::AuthContext>,
+    ) -> Result<WalletClient, String> {
+        WalletClient::connect_with(url, auth_context, websocket::ReconnectPolicy::default()).await
+    }
+
+    pub async fn connect_with(
+        url: url::Url,
+        auth_context: std::sync::Arc<context::AuthContext>,
+        policy: websocket::ReconnectPolicy,
+    ) -> Result<WalletClient, String> {
+        let connection = websocket::Connection::connect_with(url, policy).await?;
+        connection.login(auth_context).await?;
+        Ok(WalletClient { connection })
+    }
+
+    /// Current wallet balances followed by every balance change.
+    /// After a reconnect the balances are reported again.
+    pub async fn subscribe_balances(
+        &self,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<models::AccountCurrency>>, String> {
+        use futures::StreamExt;
+        let snapshots = self.connection.listen(Self::BALANCES)?;
+        let updates = self.connection.listen(Self::BALANCE_UPDATE)?;
+        self.connection.call_subscribe(
+            "subscribe_wallet_balances",
+            serde_json::json!({}),
+            &[Self::BALANCES, Self::BALANCE_UPDATE]).await?;
+        let snapshots = snapshots
+            .flat_map(|notification| {
+                let events = match websocket::read_params::<models::Balance>(notification) {
+                    Some(websocket::StreamEvent::Event(balance)) => balance.into_iter()
+                        .map(websocket::StreamEvent::Event)
+                        .collect(),
+                    Some(websocket::StreamEvent::Reconnected) => vec![websocket::StreamEvent::Reconnected],
+                    Some(websocket::StreamEvent::LoginFailed(error)) => vec![websocket::StreamEvent::LoginFailed(error)],
+                    Some(websocket::StreamEvent::RestoreFailed(error)) => vec![websocket::StreamEvent::RestoreFailed(error)],
+                    _ => Vec::new(),
+                };
+                futures::stream::iter(events)
+            });
+        let updates = updates
+            .filter_map(|notification| async move {
+                match notification {
+                    websocket::Notification::Message(_) => websocket::read_params(notification),
+                    _ => None,
+                }
+            });
+        Ok(futures::stream::select(snapshots, updates))
+    }
+
+    /// Transactions changed while the socket was down are not reported,
+    /// use `BTCClient::get_transactions` after `StreamEvent::Reconnected`.
+    pub async fn subscribe_transactions(
+        &self,
+    ) -> Result<impl futures::Stream<Item = websocket::StreamEvent<TransactionUpdate>>, String> {
+        use futures::StreamExt;
+        let updates = self.connection.listen(Self::TRANSACTION_UPDATE)?;
+        self.connection.call_subscribe(
+            "subscribe_transactions",
+            serde_json::json!({}),
+            &[Self::TRANSACTION_UPDATE]).await?;
+        Ok(updates
+            .filter_map(|notification| async move {
+                websocket::read_params(notification)
+            }))
+    }
+}
@@ -0,0 +1,578 @@
+use futures::SinkExt;
+use futures::StreamExt;
+use super::context;
+
+#[derive(Clone, Debug)]
+pub enum Notification {
+    Message(serde_json::Value),
+    /// The socket was reopened and the login and subscriptions were restored.
+    Reconnected,
+    /// The login was rejected after a reconnect, the connection is closed.
+    LoginFailed(String),
+    /// The subscription of the channel was rejected after a reconnect,
+    /// no more notifications arrive until the next reconnect restores it.
+    RestoreFailed(String),
+}
+
+/// Item of every subscription stream.
+#[derive(Clone, Debug)]
+pub enum StreamEvent<TEvent> {
+    Event(TEvent),
+    /// Notifications sent while the socket was down are lost, state should be resynced.
+    Reconnected,
+    /// The login was rejected after a reconnect, the stream ends.
+    LoginFailed(String),
+    /// The subscription was rejected after a reconnect, events stop until the next reconnect.
+    RestoreFailed(String),
+    /// Sequence numbers of `symbol` are not contiguous, state should be resynced.
+    GapDetected {
+        symbol: String,
+        expected: u64,
+        received: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReconnectPolicy {
+    pub initial_delay: std::time::Duration,
+    pub max_delay: std::time::Duration,
+    /// `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: std::time::Duration::from_secs(1),
+            max_delay: std::time::Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+type Response = tokio::sync::oneshot::Sender<Result<serde_json::Value, String>>;
+
+enum Command {
+    Request {
+        message: serde_json::Map<String, serde_json::Value>,
+        response: Response,
+        /// Channels notified by the subscription, `None` for plain requests.
+        restore: Option<Vec<String>>,
+    },
+    Login {
+        auth_context: std::sync::Arc<context::AuthContext>,
+        response: Response,
+    },
+    Listen {
+        channel: String,
+        notifications: tokio::sync::mpsc::UnboundedSender<Notification>,
+    },
+}
+
+/// JSON-RPC style socket shared by the market data, trading and wallet streams.
+/// Responses are matched to requests by `id`, notifications are routed by
+/// their `ch` (market data) or `method` (trading and wallet) field.
+/// On disconnect the socket is reopened with backoff, the login is repeated,
+/// successful subscriptions with a live listener are replayed once it is
+/// accepted and listeners receive `Notification::Reconnected`.
+pub struct Connection {
+    commands: tokio::sync::mpsc::UnboundedSender<Command>,
+}
+
+impl Connection {
+    pub async fn connect(url: url::Url) -> Result<Connection, String> {
+        Connection::connect_with(url, ReconnectPolicy::default()).await
+    }
+
+    pub async fn connect_with(
+        url: url::Url,
+        policy: ReconnectPolicy,
+    ) -> Result<Connection, String> {
+        let socket = open(&url).await?;
+        let (commands, receiver) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(run(socket, receiver, url, policy));
+        Ok(Connection { commands })
+    }
+
+    pub async fn request(
+        &self,
+        message: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<serde_json::Value, String> {
+        let (response, receiver) = tokio::sync::oneshot::channel();
+        self.send(Command::Request { message, response, restore: None })?;
+        wait(receiver).await
+    }
+
+    /// Same as `request` but the message is sent again after every reconnect,
+    /// as long as one of `channels` has a listener.
+    pub async fn subscribe(
+        &self,
+        message: serde_json::Map<String, serde_json::Value>,
+        channels: &[&str],
+    ) -> Result<serde_json::Value, String> {
+        let (response, receiver) = tokio::sync::oneshot::channel();
+        let channels = channels.iter().map(|channel| channel.to_string()).collect();
+        self.send(Command::Request { message, response, restore: Some(channels) })?;
+        wait(receiver).await
+    }
+
+    pub async fn call(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        self.request(method_message(method, params)).await
+    }
+
+    pub async fn call_subscribe(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        channels: &[&str],
+    ) -> Result<serde_json::Value, String> {
+        self.subscribe(method_message(method, params), channels).await
+    }
+
+    /// Authenticates the socket with the same key pair as the REST client.
+    /// The login is repeated with a fresh signature after every reconnect.
+    pub async fn login(
+        &self,
+        auth_context: std::sync::Arc<context::AuthContext>,
+    ) -> Result<(), String> {
+        let (response, receiver) = tokio::sync::oneshot::channel();
+        self.send(Command::Login { auth_context, response })?;
+        match wait(receiver).await? {
+            serde_json::Value::Bool(true) => Ok(()),
+            result => Err(format!("Login rejected: {:#?}", result)),
+        }
+    }
+
+    /// Notifications of `channel` received from now on.
+    pub fn listen(
+        &self,
+        channel: &str,
+    ) -> Result<impl futures::Stream<Item = Notification>, String> {
+        let (notifications, receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.send(Command::Listen {
+            channel: channel.to_owned(),
+            notifications,
+        })?;
+        Ok(futures::stream::unfold(receiver, |mut receiver| async move {
+            let notification = receiver.recv().await?;
+            Some((notification, receiver))
+        }))
+    }
+
+    fn send(&self, command: Command) -> Result<(), String> {
+        self.commands.send(command)
+            .map_err(|_| "Connection is closed".to_owned())
+    }
+}
+
+/// Deserializes a response result or notification payload, logging failures.
+pub fn read_value<TResult>(value: serde_json::Value) -> Option<TResult>
+where
+    TResult: serde::de::DeserializeOwned,
+{
+    match serde_json::from_value(value) {
+        Ok(result) => Some(result),
+        Err(error) => {
+            log::error!("Error on reading the payload: {:#?}", error);
+            None
+        },
+    }
+}
+
+/// Reads the `params` payload of a notification, passing reconnects through.
+pub fn read_params<TEvent>(notification: Notification) -> Option<StreamEvent<TEvent>>
+where
+    TEvent: serde::de::DeserializeOwned,
+{
+    match notification {
+        Notification::Message(message) => message.get("params")
+            .cloned()
+            .and_then(read_value)
+            .map(StreamEvent::Event),
+        Notification::Reconnected => Some(StreamEvent::Reconnected),
+        Notification::LoginFailed(error) => Some(StreamEvent::LoginFailed(error)),
+        Notification::RestoreFailed(error) => Some(StreamEvent::RestoreFailed(error)),
+    }
+}
+
+type Socket = tokio_tungstenite::WebSocketStream<
+    tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+type Listeners = std::collections::HashMap<
+    String,
+    Vec<tokio::sync::mpsc::UnboundedSender<Notification>>>;
+
+enum PendingRequest {
+    Request {
+        response: Response,
+        /// Kept for replay once the server accepts it.
+        restore: Option<Subscription>,
+    },
+    Login {
+        response: Response,
+        auth_context: std::sync::Arc<context::AuthContext>,
+    },
+    /// Subscription replayed after reconnect.
+    Replay(Subscription),
+}
+
+#[derive(Clone)]
+struct Subscription {
+    message: serde_json::Map<String, serde_json::Value>,
+    channels: Vec<String>,
+}
+
+impl Subscription {
+    /// Method and parameters, equal subscriptions are restored once.
+    fn key(&self) -> String {
+        serde_json::Value::Object(self.message.clone()).to_string()
+    }
+}
+
+type Pending = std::collections::HashMap<u64, PendingRequest>;
+
+/// Time to wait for the login response after a reconnect.
+const LOGIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+struct State {
+    next_id: u64,
+    pending: Pending,
+    listeners: Listeners,
+    auth_context: Option<std::sync::Arc<context::AuthContext>>,
+    restore: std::collections::HashMap<String, Subscription>,
+}
+
+enum SessionEnd {
+    Closed,
+    Disconnected,
+    LoginFailed(String),
+}
+
+async fn open(url: &url::Url) -> Result<Socket, String> {
+    match tokio_tungstenite::connect_async(url.as_str()).await {
+        Ok((socket, _response)) => Ok(socket),
+        Err(error) => Err(format!("Failed to connect to {}: {:#?}", url, error)),
+    }
+}
+
+async fn wait(
+    receiver: tokio::sync::oneshot::Receiver<Result<serde_json::Value, String>>,
+) -> Result<serde_json::Value, String> {
+    match receiver.await {
+        Ok(result) => result,
+        Err(_) => Err("Connection closed before response".to_owned()),
+    }
+}
+
+fn method_message(
+    method: &str,
+    params: serde_json::Value,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut message = serde_json::Map::new();
+    message.insert("method".to_owned(), method.into());
+    message.insert("params".to_owned(), params);
+    message
+}
+
+fn login_message(
+    auth_context: &context::AuthContext,
+) -> serde_json::Map<String, serde_json::Value> {
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    method_message("login", serde_json::json!({
+        "type": "HS256",
+        "api_key": auth_context.public_key(),
+        "timestamp": timestamp,
+        "signature": auth_context.hmac(&timestamp.to_string()),
+    }))
+}
+
+async fn run(
+    mut socket: Socket,
+    mut commands: tokio::sync::mpsc::UnboundedReceiver<Command>,
+    url: url::Url,
+    policy: ReconnectPolicy,
+) {
+    let mut state = State {
+        next_id: 1,
+        pending: Pending::new(),
+        listeners: Listeners::new(),
+        auth_context: None,
+        restore: std::collections::HashMap::new(),
+    };
+    let mut reconnected = false;
+    loop {
+        match session(socket, &mut commands, &mut state, reconnected).await {
+            SessionEnd::Closed => break,
+            SessionEnd::LoginFailed(error) => {
+                log::error!("Closing connection to {}: {}", url, error);
+                notify(&mut state.listeners, Notification::LoginFailed(error));
+                break;
+            },
+            SessionEnd::Disconnected => (),
+        }
+        fail_pending(&mut state.pending, "Connection lost before response");
+        socket = match reconnect(&url, policy).await {
+            Some(socket) => socket,
+            None => break,
+        };
+        reconnected = true;
+    }
+    fail_pending(&mut state.pending, "Connection closed before response");
+}
+
+fn notify(listeners: &mut Listeners, notification: Notification) {
+    for senders in listeners.values_mut() {
+        senders.retain(|sender| sender.send(notification.clone()).is_ok());
+    }
+}
+
+fn fail_pending(pending: &mut Pending, error: &str) {
+    for (_id, request) in pending.drain() {
+        match request {
+            PendingRequest::Request { response, .. } | PendingRequest::Login { response, .. } => {
+                let _ = response.send(Err(error.to_owned()));
+            },
+            PendingRequest::Replay(_) => (),
+        }
+    }
+}
+
+async fn reconnect(url: &url::Url, policy: ReconnectPolicy) -> Option<Socket> {
+    let mut delay = policy.initial_delay;
+    let mut attempt = 0;
+    loop {
+        if let Some(max_attempts) = policy.max_attempts {
+            if attempt >= max_attempts {
+                log::error!("Giving up reconnecting to {} after {} attempts", url, attempt);
+                return None;
+            }
+        }
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+        match open(url).await {
+            Ok(socket) => {
+                log::warn!("Reconnected to {} after {} attempts", url, attempt);
+                return Some(socket);
+            },
+            Err(error) => log::error!("{}", error),
+        }
+        delay = std::cmp::min(delay * 2, policy.max_delay);
+    }
+}
+
+type Sink = futures::stream::SplitSink<Socket, tokio_tungstenite::tungstenite::Message>;
+type Stream = futures::stream::SplitStream<Socket>;
+
+async fn session(
+    socket: Socket,
+    commands: &mut tokio::sync::mpsc::UnboundedReceiver<Command>,
+    state: &mut State,
+    reconnected: bool,
+) -> SessionEnd {
+    let (mut sink, mut stream) = socket.split();
+    if reconnected {
+        if let Some(auth_context) = state.auth_context.clone() {
+            if let Err(end) = relogin(&mut sink, &mut stream, state, &auth_context).await {
+                return end;
+            }
+        }
+        prune(state);
+        let subscriptions = state.restore.values().cloned().collect::<Vec<_>>();
+        for subscription in subscriptions {
+            let id = state.next_id;
+            state.next_id += 1;
+            if send(&mut sink, subscription.message.clone(), id).await.is_err() {
+                return SessionEnd::Disconnected;
+            }
+            state.pending.insert(id, PendingRequest::Replay(subscription));
+        }
+        notify(&mut state.listeners, Notification::Reconnected);
+    }
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(Command::Request { message, response, restore }) => {
+                    let id = state.next_id;
+                    state.next_id += 1;
+                    let restore = restore.map(|channels| Subscription {
+                        message: message.clone(),
+                        channels,
+                    });
+                    match send(&mut sink, message, id).await {
+                        Ok(()) => {
+                            state.pending.insert(id, PendingRequest::Request { response, restore });
+                        },
+                        Err(error) => {
+                            let _ = response.send(Err(error));
+                            return SessionEnd::Disconnected;
+                        },
+                    }
+                },
+                Some(Command::Login { auth_context, response }) => {
+                    let id = state.next_id;
+                    state.next_id += 1;
+                    match send(&mut sink, login_message(&auth_context), id).await {
+                        Ok(()) => {
+                            state.pending.insert(id, PendingRequest::Login { response, auth_context });
+                        },
+                        Err(error) => {
+                            let _ = response.send(Err(error));
+                            return SessionEnd::Disconnected;
+                        },
+                    }
+                },
+                Some(Command::Listen { channel, notifications }) => {
+                    state.listeners.entry(channel).or_default().push(notifications);
+                },
+                None => return SessionEnd::Closed,
+            },
+            message = stream.next() => match message {
+                Some(Ok(tokio_tungstenite::tungstenite::Message::Text(text))) => {
+                    let message: serde_json::Value = match serde_json::from_str(&text) {
+                        Ok(message) => message,
+                        Err(error) => {
+                            log::error!("Error on reading the message: {:#?}", error);
+                            log::error!("Json: {:#?}", text);
+                            continue;
+                        },
+                    };
+                    dispatch(message, state);
+                },
+                Some(Ok(tokio_tungstenite::tungstenite::Message::Close(frame))) => {
+                    log::warn!("WebSocket closed: {:#?}", frame);
+                    return SessionEnd::Disconnected;
+                },
+                Some(Ok(_)) => (),
+                Some(Err(error)) => {
+                    log::error!("WebSocket error: {:#?}", error);
+                    return SessionEnd::Disconnected;
+                },
+                None => return SessionEnd::Disconnected,
+            },
+        }
+    }
+}
+
+/// Repeats the login and waits for its response before anything else is sent.
+/// Other messages received meanwhile are dispatched as usual.
+async fn relogin(
+    sink: &mut Sink,
+    stream: &mut Stream,
+    state: &mut State,
+    auth_context: &context::AuthContext,
+) -> Result<(), SessionEnd> {
+    let id = state.next_id;
+    state.next_id += 1;
+    if send(sink, login_message(auth_context), id).await.is_err() {
+        return Err(SessionEnd::Disconnected);
+    }
+    let deadline = tokio::time::Instant::now() + LOGIN_TIMEOUT;
+    loop {
+        let message = match tokio::time::timeout_at(deadline, stream.next()).await {
+            Ok(Some(Ok(tokio_tungstenite::tungstenite::Message::Text(text)))) => text,
+            Ok(Some(Ok(tokio_tungstenite::tungstenite::Message::Close(_)))) => {
+                return Err(SessionEnd::Disconnected);
+            },
+            Ok(Some(Ok(_))) => continue,
+            Ok(Some(Err(_))) | Ok(None) => return Err(SessionEnd::Disconnected),
+            Err(_) => {
+                log::error!("No login response within {:?}", LOGIN_TIMEOUT);
+                return Err(SessionEnd::Disconnected);
+            },
+        };
+        let message: serde_json::Value = match serde_json::from_str(&message) {
+            Ok(message) => message,
+            Err(error) => {
+                log::error!("Error on reading the message: {:#?}", error);
+                continue;
+            },
+        };
+        if message.get("id").and_then(|value| value.as_u64()) != Some(id) {
+            dispatch(message, state);
+            continue;
+        }
+        return match response_result(id, &message) {
+            Ok(serde_json::Value::Bool(true)) => Ok(()),
+            Ok(result) => Err(SessionEnd::LoginFailed(format!("Login rejected: {:#?}", result))),
+            Err(error) => Err(SessionEnd::LoginFailed(error)),
+        };
+    }
+}
+
+async fn send(
+    sink: &mut Sink,
+    mut message: serde_json::Map<String, serde_json::Value>,
+    id: u64,
+) -> Result<(), String> {
+    message.insert("id".to_owned(), id.into());
+    let text = serde_json::Value::Object(message).to_string();
+    sink.send(tokio_tungstenite::tungstenite::Message::Text(text)).await
+        .map_err(|error| format!("Failed to send request: {:#?}", error))
+}
+
+fn response_result(id: u64, message: &serde_json::Value) -> Result<serde_json::Value, String> {
+    match message.get("error") {
+        Some(error) => Err(format!("Error on request {}: {:#?}", id, error)),
+        None => Ok(message.get("result").cloned().unwrap_or(serde_json::Value::Null)),
+    }
+}
+
+fn dispatch(message: serde_json::Value, state: &mut State) {
+    if let Some(id) = message.get("id").and_then(|id| id.as_u64()) {
+        if let Some(request) = state.pending.remove(&id) {
+            let result = response_result(id, &message);
+            match request {
+                PendingRequest::Request { response, restore } => {
+                    if let (Ok(_), Some(restore)) = (&result, restore) {
+                        state.restore.insert(restore.key(), restore);
+                    }
+                    let _ = response.send(result);
+                },
+                PendingRequest::Login { response, auth_context } => {
+                    if let Ok(serde_json::Value::Bool(true)) = &result {
+                        state.auth_context = Some(auth_context);
+                    }
+                    let _ = response.send(result);
+                },
+                PendingRequest::Replay(subscription) => if let Err(error) = result {
+                    log::error!("Failed to restore after reconnect: {}", error);
+                    for channel in subscription.channels.iter() {
+                        if let Some(senders) = state.listeners.get_mut(channel) {
+                            senders.retain(|sender| sender
+                                .send(Notification::RestoreFailed(error.clone()))
+                                .is_ok());
+                        }
+                    }
+                },
+            }
+        }
+        return;
+    }
+    let channel = message.get("ch")
+        .or_else(|| message.get("method"))
+        .and_then(|channel| channel.as_str())
+        .map(|channel| channel.to_owned());
+    if let Some(channel) = channel {
+        if let Some(senders) = state.listeners.get_mut(&channel) {
+            senders.retain(|sender| sender.send(Notification::Message(message.clone())).is_ok());
+            if senders.is_empty() {
+                prune(state);
+            }
+        }
+    }
+}
+
+/// Drops closed listeners and the subscriptions no listener is left for.
+fn prune(state: &mut State) {
+    for senders in state.listeners.values_mut() {
+        senders.retain(|sender| !sender.is_closed());
+    }
+    state.listeners.retain(|_, senders| !senders.is_empty());
+    let listeners = &state.listeners;
+    state.restore.retain(|_, subscription| subscription.channels.iter()
+        .any(|channel| listeners.contains_key(channel)));
+}